@@ -3,32 +3,78 @@
 // Final Anchor program with Orao VRF integration (English comments)
 // --------------------------------------------------------------------------
 // Key properties:
-// - Allowed team sizes: 1, 2, 5 (validated on create)
-// - side: u8 as bit → 0 = team1, 1 = team2
-// - Min stake: 0.05 SOL (50_000_000 lamports)
-// - Fixed platform fee: 1% (sent to hardcoded TREASURY_PUBKEY)
+// - Allowed team shapes: 1v1, 2v2, 5v5 and DeathMatch 1v10, 2v20, 4v40 (either orientation, validated on create)
+// - Both sides put the same total into the pot: a seat on the smaller side stakes stake_lamports * (larger / smaller)
+// - game / game_mode / arena_type are compact registry IDs (GameKey); each combination must be registered
+//   in a GameEntry PDA (labels, team sizes, stake range). Lobby stores the 3-byte key at a fixed offset.
+// - side: u8 as bit → 0 = team1, 1 = team2; joins also accept SIDE_ANY (emptier team, ties → team1)
+// - Min stake: 0.05 SOL by default (GlobalConfig.min_stake_lamports)
+// - Platform fee: 1% by default, capped at 10% (GlobalConfig.fee_bps, sent to GlobalConfig.treasury)
+// - Optional capped cranker reward (GlobalConfig.crank_reward_bps): carved out of the fee on resolve_match,
+//   withheld pro rata from the stakes on permissionless refunds (timeout_refund / expire_refund)
+// - Admin and treasury live in the singleton GlobalConfig PDA (initialize_config / update_config)
+// - Admin handover is two-step (propose_admin → accept_admin)
+// - Optional M-of-N council: when enabled, force_refund and treasury/config changes need an approved AdminProposal
+// - Grantable roles (RoleGrant PDAs): Pauser, RefundOperator, FeeManager, Treasurer; every privileged call emits PrivilegeUsed
+//   (enabling the council voids earlier grants except Pauser; grants made afterwards are council-approved)
 // - Creator pays and joins immediately on create_lobby
-// - Exactly one active lobby per creator enforced by ActiveLobby PDA
+// - rematch re-creates a Resolved lobby (same game key, shape and stake); its seats stay reserved for the
+//   previous participants for REMATCH_RESERVE_SECS, then open to anyone. A lobby can be rematched once
+//   (Lobby.rematch_lobby). The new creator prepays the reserved seats; a previous player who takes theirs
+//   claims that prepaid seat and pays no seat rent. close_lobby keeps a Resolved lobby around for
+//   REMATCH_WINDOW_SECS (unless its creator closes it) so a rematch can still read it
+// - Lobby account grows by one seat per join (realloc); each joiner pays the rent for their own seat,
+//   the creator pays the header + first seat. Seat rent stays in the PDA until the account is closed.
+//   Migrated legacy lobbies keep their full-size allocation, paid by the creator (Lobby.seat_rent_prepaid).
+// - Concurrent lobbies per creator are capped by GlobalConfig.max_active_lobbies (1 by default) through indexed
+//   ActiveLobby slots: slot 0 = ["active", creator] (the original PDA), slot N = ["active", creator, [N]]
 // - Auto VRF request on the LAST join (no off-chain picker) → status moves to Pending
+// - quick_play joins the first matching Open lobby among client-supplied candidates, or creates one
 // - Orao VRF oracles fulfill randomness automatically (sub-second)
-// - Refund is only possible from Open state (i.e., before VRF request) and after 2 minutes
+// - Randomness provider is pluggable (RandomnessProvider: Orao or Switchboard On-Demand). GlobalConfig picks it
+//   (set_randomness_provider, e.g. to fail over during an outage); a lobby records the one its request went to
+//   Switchboard accounts must be controlled by the final joiner; after a reveal the lobby can only be settled, not
+//   refunded, and re-committing or closing the account before settlement loses the match for the final joiner's side
+// - 1v1 lobbies (<= MAX_COMMIT_REVEAL_PLAYERS) can skip the oracle: created with a commitment, every joiner commits,
+//   and once full each player reveals within REVEAL_WINDOW_SECS. resolve_match combines the reveals; a player who
+//   misses the deadline forfeits their stake and the revealer wins the match (Resolved, no platform fee, see
+//   forfeit_payouts). If neither revealed, both are refunded
+// - Admin circuit breaker: create / join / resolve can be paused independently; refunds are never paused
+// - Refund is only possible from Open state (i.e., before VRF request) and after the refund lock (2 minutes by default)
+// - Every lobby has a creator-chosen expires_at (bounded by GlobalConfig lobby TTLs); past it the lobby takes no joins
+//   and anyone can refund it (expire_refund)
+// - Pending lobbies whose VRF request isn't fulfilled within GlobalConfig.vrf_timeout_secs can be refunded by anyone
+// - force_refund needs the lobby stuck for GlobalConfig.force_refund_min_stuck_secs and never touches fulfilled randomness
 // - Careful use of remaining_accounts for payouts to ensure target AccountInfos are present
 //
 // Notes:
 // - Orao VRF uses seed-based requests: request creates PDA, oracles fulfill automatically
 // - Request is made via CPI call in join_side_final
-// - ResolveMatch reads fulfilled randomness from Orao VRF account
+// - The VRF seed is derived on-chain from the lobby (derive_vrf_seed); clients only pick a nonce and pass
+//   the matching Orao request PDA, so a stale or squatted seed can't be forced onto a lobby
+// - Switchboard On-Demand is pull based: the client commits its randomness account in the slot before the final
+//   join and bundles the oracle's reveal with resolve_match (see switchboard.rs)
+// - ResolveMatch reads the randomness from the lobby's provider account
 // - ActiveLobby is closed after final resolution or refund (returns rent to creator).
+// - Settled (Resolved / Refunded) Lobby PDAs can be closed by anyone (close_lobby): joiners get their seat rent
+//   back, the creator gets the rest. The address (creator, lobby_id) can then be reused; Lobby.created_slot
+//   (also in LobbyCreated / LobbyClosed) tells the lobbies that lived there apart.
 
 use anchor_lang::prelude::{*, Context, Program};
 use anchor_lang::system_program::System;
 use anchor_lang::solana_program::system_instruction;
 // Orao VRF types and constants
 use orao_solana_vrf::program::OraoVrf;
-use orao_solana_vrf::state::NetworkState;
+use orao_solana_vrf::state::{NetworkState, Randomness, RandomnessAccountData, RandomnessV2};
 use orao_solana_vrf::CONFIG_ACCOUNT_SEED;
 use orao_solana_vrf::RANDOMNESS_ACCOUNT_SEED;
 
+pub mod rng;
+use rng::RngStream;
+
+pub mod switchboard;
+use switchboard::SwitchboardRandomness;
+
 // Security contact information
 #[cfg(not(feature = "no-entrypoint"))]
 use solana_security_txt::security_txt;
@@ -53,24 +99,84 @@ declare_id!("F2LhVGUa9yLbYVYujYMPyckqWmsokHE9wym7ceGHWUMZ");
 // VRFzZoJdhFWL8rkvu87LpKM3RbcVezpMEc6X5GVDr7y
 pub use orao_solana_vrf::ID as ORAO_VRF_PROGRAM_ID;
 
-// Hardcoded admin and treasury addresses (SECURE!)
-// Admin: Can authorize refunds and resolves
-pub const ADMIN_PUBKEY: Pubkey = anchor_lang::solana_program::pubkey!("7tjJ6oCmrGMnin2kMf8msTqqxSYgq3J8wBW4zoheRdPz");
-// Treasury: Receives platform fees (1%)
-pub const TREASURY_PUBKEY: Pubkey = anchor_lang::solana_program::pubkey!("5tFuAw8fBq9mPgj26NbYHwMa9WoJm4cUScbaCd6TQoJ6");
-
 // PDA seeds
 const SEED_LOBBY:  &[u8] = b"lobby";
 const SEED_ACTIVE: &[u8] = b"active";
+const SEED_CONFIG: &[u8] = b"config";
+const SEED_PROPOSAL: &[u8] = b"proposal";
+const SEED_ROLE: &[u8] = b"role";
+const SEED_TREASURY: &[u8] = b"treasury";
+const SEED_GAME: &[u8] = b"game";
+
+// Economics (defaults written by initialize_config, adjustable via update_economics)
+const DEFAULT_PLATFORM_FEE_BPS: u16 = 100;            // 1%
+const DEFAULT_MIN_STAKE_LAMPORTS: u64 = 50_000_000;   // 0.05 SOL
+const DEFAULT_REFUND_LOCK_SECS: i64 = 120;            // 2 minutes
+const DEFAULT_CRANK_REWARD_BPS: u16 = 0;              // disabled
+const DEFAULT_MAX_CRANK_REWARD_LAMPORTS: u64 = 5_000_000; // 0.005 SOL
+
+// Economics bounds (enforced on update_economics)
+const MAX_PLATFORM_FEE_BPS: u16 = 1_000;              // 10%
+const MAX_REFUND_LOCK_SECS: i64 = 86_400;             // 1 day
+const MAX_CRANK_REWARD_BPS: u16 = 100;                // 1% of the pot
+const MAX_CRANK_REWARD_LAMPORTS: u64 = 100_000_000;   // 0.1 SOL hard cap
+
+// Timeouts (defaults written by initialize_config, adjustable via update_timeouts)
+const DEFAULT_VRF_TIMEOUT_SECS: i64 = 600;            // 10 minutes
+const MIN_VRF_TIMEOUT_SECS: i64 = 60;                 // Orao normally fulfills in seconds
+const MAX_VRF_TIMEOUT_SECS: i64 = 7 * 86_400;         // 1 week
+const DEFAULT_FORCE_REFUND_MIN_STUCK_SECS: i64 = 3_600; // 1 hour
+const MAX_FORCE_REFUND_MIN_STUCK_SECS: i64 = 7 * 86_400;
+const DEFAULT_MIN_LOBBY_TTL_SECS: i64 = 120;          // 2 minutes
+const DEFAULT_MAX_LOBBY_TTL_SECS: i64 = 86_400;       // 1 day (also applied to migrated legacy lobbies)
+const MAX_LOBBY_TTL_SECS: i64 = 30 * 86_400;          // 30 days
+
+// Pause switches (bits of GlobalConfig.pause_flags). Refunds are never paused.
+pub const PAUSE_CREATE: u8  = 1 << 0;                 // create_lobby
+pub const PAUSE_JOIN: u8    = 1 << 1;                 // join_side / join_side_final
+pub const PAUSE_RESOLVE: u8 = 1 << 2;                 // resolve_match
+const PAUSE_ALL: u8 = PAUSE_CREATE | PAUSE_JOIN | PAUSE_RESOLVE;
+
+// Admin council (M-of-N approvals)
+const MAX_COUNCIL_SIGNERS: usize = 10;
 
-// Economics
-const PLATFORM_FEE_BPS: u64 = 100;            // 1%
-const MIN_STAKE_LAMPORTS: u64 = 50_000_000;   // 0.05 SOL
-const REFUND_LOCK_SECS: i64 = 120;            // 2 minutes
+// Join sentinel: let the program pick the emptier side (see Lobby::pick_side)
+pub const SIDE_ANY: u8 = u8::MAX;
 
-// Team sizing
-const MAX_TEAM_SIZE_ALLOC: usize = 5;         // allocation cap
-const ALLOWED_TEAM_SIZES: [u8; 3] = [1, 2, 5]; // allowed sizes
+// Domain tag of derive_vrf_seed
+const VRF_SEED_DOMAIN: &[u8] = b"solanapvp:vrf-seed:v1";
+
+// Commit-reveal lobbies: domain tags (see commitment_hash / reveal_randomness), size cap and reveal window
+const COMMIT_DOMAIN: &[u8] = b"solanapvp:commit:v1";
+const REVEAL_RANDOMNESS_DOMAIN: &[u8] = b"solanapvp:reveals:v1";
+// 1v1 only: forfeiting is per player, so in a team lobby a member of the side that is about to lose could
+// withhold their reveal and lose just their own stake instead of the whole side's
+const MAX_COMMIT_REVEAL_PLAYERS: usize = 2;
+const REVEAL_WINDOW_SECS: i64 = 300;                  // 5 minutes after the lobby fills
+
+// Rematch seat reservation window
+const REMATCH_RESERVE_SECS: i64 = 60;
+// How long a Resolved lobby stays open for rematch: before that only its creator can close it
+const REMATCH_WINDOW_SECS: i64 = 600;                 // 10 minutes
+
+// Concurrent hosted lobbies per creator (ActiveLobby slots)
+const DEFAULT_MAX_ACTIVE_LOBBIES: u8 = 1;
+const MAX_ACTIVE_LOBBIES: u8 = 10;
+
+// Team sizing: allowed (smaller side, larger side) shapes. Larger is always a multiple of smaller.
+const ALLOWED_TEAM_SHAPES: [(u8, u8); 6] = [(1, 1), (2, 2), (5, 5), (1, 10), (2, 20), (4, 40)];
+const MAX_GAME_SHAPES: usize = 9;              // registry cap: 3 symmetric shapes + 3 asymmetric in both orientations
+
+// Game label caps (GameEntry labels and the LegacyLobby allocation)
+const MAX_GAME_LEN: usize = 32;
+const MAX_GAME_MODE_LEN: usize = 16;
+const MAX_ARENA_TYPE_LEN: usize = 32;
+const MAX_TEAM_SIZE_STR_LEN: usize = 16;
+
+// Account discriminators. The compact Lobby layout gets its own discriminator so that
+// lobbies created before it (LegacyLobby, default "account:Lobby") still decode unambiguously.
+pub const LOBBY_DISCRIMINATOR: [u8; 8] = [98, 192, 223, 222, 175, 35, 204, 167];        // sha256("account:LobbyV2")[..8]
+pub const LEGACY_LOBBY_DISCRIMINATOR: [u8; 8] = [167, 194, 217, 163, 92, 92, 103, 49];  // sha256("account:Lobby")[..8]
 
 // ------------------------------ Types / Errors ------------------------------
 
@@ -82,6 +188,134 @@ pub enum LobbyStatus {
     Refunded, // refunded to participants
 }
 
+// Compact game configuration: registry IDs for game / game_mode / arena_type.
+// Labels ("PickHigher", "3x9", "DeathMatch") live in the GameEntry PDA seeded by these IDs.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GameKey {
+    pub game: u8,
+    pub game_mode: u8,
+    pub arena_type: u8,
+}
+impl GameKey {
+    pub fn seed(&self) -> [u8; 3] {
+        [self.game, self.game_mode, self.arena_type]
+    }
+}
+
+// Seats per side. Symmetric (2v2) or asymmetric DeathMatch (1v10: team1_size = 1, team2_size = 10).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TeamShape {
+    pub team1_size: u8,
+    pub team2_size: u8,
+}
+impl TeamShape {
+    pub fn is_allowed(&self) -> bool {
+        let lo = self.team1_size.min(self.team2_size);
+        let hi = self.team1_size.max(self.team2_size);
+        ALLOWED_TEAM_SHAPES.contains(&(lo, hi))
+    }
+}
+
+// Sensitive admin actions. With the council enabled, each one must be approved through an AdminProposal
+// carrying exactly the same action before the matching instruction accepts it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum AdminAction {
+    ForceRefund { lobby: Pubkey },
+    UpdateTreasury { treasury: Pubkey },
+    UpdateEconomics {
+        fee_bps: u16,
+        min_stake_lamports: u64,
+        refund_lock_secs: i64,
+        crank_reward_bps: u16,
+        max_crank_reward_lamports: u64,
+    },
+    UpdateTimeouts {
+        vrf_timeout_secs: i64,
+        force_refund_min_stuck_secs: i64,
+        min_lobby_ttl_secs: i64,
+        max_lobby_ttl_secs: i64,
+    },
+    ProposeAdmin { new_admin: Pubkey },
+    SetCouncil { signers: Vec<Pubkey>, threshold: u8 },
+    GrantRole { role: Role, grantee: Pubkey },
+    RevokeRole { role: Role, grantee: Pubkey },
+    WithdrawTreasury { destination: Pubkey, amount: u64 },
+    SetGame {
+        game_key: GameKey,
+        game: String,
+        game_mode: String,
+        arena_type: String,
+        team_shapes: Vec<TeamShape>,
+        min_stake_lamports: u64,
+        max_stake_lamports: u64,
+        enabled: bool,
+    },
+    RemoveGame { game_entry: Pubkey },
+    SetLobbyLimit { max_active_lobbies: u8 },
+    SetRandomnessProvider { provider: RandomnessProvider },
+}
+impl AdminAction {
+    // Largest variant: SetCouncil = tag(1) + vec(4 + 32 * MAX) + threshold(1)
+    pub const MAX_SIZE: usize = 1 + 4 + 32 * MAX_COUNCIL_SIGNERS + 1;
+}
+
+// Authority a privileged call was made with. Admin / Council are implicit;
+// the remaining roles are granted to individual keys through RoleGrant PDAs.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    Admin,          // GlobalConfig.admin
+    Council,        // approved AdminProposal
+    Pauser,         // set_pause (pause only, unpausing stays with admin)
+    RefundOperator, // refund / force_refund of any lobby
+    FeeManager,     // update_economics (incl. cranker reward)
+    Treasurer,      // withdraw_treasury (without a council; with one, withdrawals need a proposal)
+}
+impl Role {
+    pub fn is_grantable(&self) -> bool {
+        !matches!(self, Role::Admin | Role::Council)
+    }
+}
+
+// Why force_refund was used, reported in ForceRefundAudit.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ForceRefundReason {
+    VrfUnavailable,   // randomness provider never fulfilled the request
+    BadVrfRequest,    // lobby points at a wrong / unusable randomness account
+    AbandonedLobby,   // Open lobby nobody is going to fill
+    Incident,         // operational incident (see off-chain report)
+    Other,
+}
+
+// Source of a lobby's randomness (GlobalConfig.randomness_provider, recorded on the lobby at request time;
+// CommitReveal is chosen by the creator instead).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RandomnessProvider {
+    Orao,                // request_v2 CPI, fulfilled by Orao oracles
+    SwitchboardOnDemand, // client-committed randomness account, revealed in the resolve transaction
+    CommitReveal,        // oracle-free: players commit on join and reveal once full (per-lobby mode, never the config default)
+}
+
+// Privileged instruction reported in PrivilegeUsed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PrivilegedOp {
+    Refund,
+    ForceRefund,
+    Pause,
+    Unpause,
+    UpdateTreasury,
+    UpdateEconomics,
+    UpdateTimeouts,
+    ProposeAdmin,
+    SetCouncil,
+    GrantRole,
+    RevokeRole,
+    WithdrawTreasury,
+    SetGame,
+    RemoveGame,
+    SetLobbyLimit,
+    SetRandomnessProvider,
+}
+
 // ------------------------------ Events ------------------------------
 
 #[event]
@@ -89,15 +323,24 @@ pub struct LobbyCreated {
     pub lobby: Pubkey,
     pub lobby_id: u64,
     pub creator: Pubkey,
-    pub stake_lamports: u64,
-    pub team_size: u8,
+    pub stake_lamports: u64, // per seat on the larger side (smaller side scales up)
+    pub team1_size: u8,
+    pub team2_size: u8,
     pub created_at: i64,
-    // NEW: Game configuration
-    pub game: String,
-    pub game_mode: String,
-    pub arena_type: String,
-    pub team_size_str: String,
-    pub creator_side: u8, // NEW: Which team creator joined (0 or 1)
+    pub game_key: GameKey, // registry IDs (labels in the GameEntry PDA)
+    pub creator_side: u8,  // Which team creator joined (0 or 1)
+    pub expires_at: i64,   // no joins after this; anyone can refund (expire_refund)
+    pub active_slot: u8,   // creator's ActiveLobby slot holding this lobby
+    pub randomness_provider: RandomnessProvider, // CommitReveal, or the config provider at creation
+    pub created_slot: u64, // with `lobby`, identifies this lobby (a closed lobby's address can be re-created)
+}
+
+#[event]
+pub struct LobbyRematched {
+    pub lobby: Pubkey,
+    pub previous_lobby: Pubkey,
+    pub reserved_count: u8,
+    pub reserved_until: i64,
 }
 
 #[event]
@@ -108,17 +351,37 @@ pub struct PlayerJoined {
     pub team1_count: u8,
     pub team2_count: u8,
     pub is_full: bool,
-    pub vrf_request: Pubkey, // Orao VRF request account (set when full)
+    pub vrf_request: Pubkey, // provider randomness account (set when full)
+}
+
+#[event]
+pub struct PlayerLeft {
+    pub lobby: Pubkey,
+    pub player: Pubkey,
+    pub side: u8,
+    pub team1_count: u8,
+    pub team2_count: u8,
+    pub refunded: u64, // stake + seat rent returned to the player
 }
 
 #[event]
 pub struct LobbyResolved {
     pub lobby: Pubkey,
     pub winner_side: u8,
-    pub randomness_value: u64, // Orao VRF randomness for transparency!
+    pub randomness_value: u64, // first 8 bytes of the provider randomness, for transparency
+    pub randomness_provider: RandomnessProvider,
     pub total_pot: u64,
-    pub platform_fee: u64,
+    pub platform_fee: u64,     // sent to treasury (after the cranker reward)
     pub payout_per_winner: u64,
+    pub cranker: Pubkey,
+    pub crank_reward: u64,     // carved out of the platform fee
+}
+
+#[event]
+pub struct RandomnessWithdrawn {
+    pub lobby: Pubkey,
+    pub vrf_request: Pubkey,
+    pub loser_side: u8, // side of the final joiner, who supplied (and controls) the randomness account
 }
 
 #[event]
@@ -126,11 +389,196 @@ pub struct LobbyRefunded {
     pub lobby: Pubkey,
     pub refunded_count: u8,
     pub total_refunded: u64,
+    pub cranker: Pubkey,       // signer that triggered the refund
+    pub crank_reward: u64,     // withheld from the stakes (permissionless refunds only)
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct EconomicsUpdated {
+    pub old_fee_bps: u16,
+    pub new_fee_bps: u16,
+    pub old_min_stake_lamports: u64,
+    pub new_min_stake_lamports: u64,
+    pub old_refund_lock_secs: i64,
+    pub new_refund_lock_secs: i64,
+    pub old_crank_reward_bps: u16,
+    pub new_crank_reward_bps: u16,
+    pub old_max_crank_reward_lamports: u64,
+    pub new_max_crank_reward_lamports: u64,
+}
+
+#[event]
+pub struct TimeoutsUpdated {
+    pub old_vrf_timeout_secs: i64,
+    pub new_vrf_timeout_secs: i64,
+    pub old_force_refund_min_stuck_secs: i64,
+    pub new_force_refund_min_stuck_secs: i64,
+    pub old_min_lobby_ttl_secs: i64,
+    pub new_min_lobby_ttl_secs: i64,
+    pub old_max_lobby_ttl_secs: i64,
+    pub new_max_lobby_ttl_secs: i64,
+}
+
+#[event]
+pub struct AdminTransferProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey, // Pubkey::default() = proposal cancelled
+}
+
+#[event]
+pub struct AdminTransferred {
+    pub old_admin: Pubkey,
+    pub new_admin: Pubkey,
+}
+
+#[event]
+pub struct CouncilUpdated {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8, // 0 = council disabled
+}
+
+#[event]
+pub struct ProposalCreated {
+    pub proposal: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+}
+
+#[event]
+pub struct ProposalApproved {
+    pub proposal: Pubkey,
+    pub signer: Pubkey,
+    pub approvals: u8,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct ProposalExecuted {
+    pub proposal: Pubkey,
+    pub executor: Pubkey,
+}
+
+#[event]
+pub struct ProposalClosed {
+    pub proposal: Pubkey,
+    pub executed: bool,
+}
+
+#[event]
+pub struct PrivilegeUsed {
+    pub authority: Pubkey,
+    pub role: Role,
+    pub op: PrivilegedOp,
+}
+
+#[event]
+pub struct ForceRefundAudit {
+    pub lobby: Pubkey,
+    pub authority: Pubkey,
+    pub role: Option<Role>, // None = the lobby creator
+    pub reason: ForceRefundReason,
+    pub status: LobbyStatus, // status before the refund
+    pub stuck_secs: i64,     // time since the lobby was created (Open) or filled (Pending)
+}
+
+#[event]
+pub struct RoleGranted {
+    pub role: Role,
+    pub grantee: Pubkey,
+    pub granted_by: Pubkey,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub role: Role,
+    pub grantee: Pubkey,
+    pub revoked_by: Pubkey,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct GameEntryUpdated {
+    pub game_entry: Pubkey,
+    pub game_key: GameKey,
+    pub game: String,
+    pub game_mode: String,
+    pub arena_type: String,
+    pub team_shapes: Vec<TeamShape>,
+    pub min_stake_lamports: u64,
+    pub max_stake_lamports: u64,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct GameEntryRemoved {
+    pub game_entry: Pubkey,
+}
+
+#[event]
+pub struct LobbyClosed {
+    pub lobby: Pubkey,
+    pub closed_by: Pubkey,
+    pub creator_rent: u64,     // header + creator seat rent returned to the creator
+    pub seat_rent_total: u64,  // seat rent returned to the other participants
+    pub created_slot: u64,     // matches the LobbyCreated of the lobby being closed
+}
+
+#[event]
+pub struct LobbyLimitUpdated {
+    pub old_max_active_lobbies: u8,
+    pub new_max_active_lobbies: u8,
+}
+
+#[event]
+pub struct SecretRevealed {
+    pub lobby: Pubkey,
+    pub player: Pubkey,
+    pub revealed_count: u8,
+    pub player_count: u8,
+}
+
+#[event]
+pub struct RevealsMissed {
+    pub lobby: Pubkey,
+    pub players: Vec<Pubkey>, // committed but didn't reveal by the deadline; their stakes go to the revealers
+}
+
+#[event]
+pub struct RandomnessProviderUpdated {
+    pub old_provider: RandomnessProvider,
+    pub new_provider: RandomnessProvider,
+}
+
+#[event]
+pub struct LobbyMigrated {
+    pub lobby: Pubkey,
+    pub game_key: GameKey,
+}
+
+#[event]
+pub struct PauseUpdated {
+    pub authority: Pubkey,
+    pub flags: u8,       // switches touched by this call
+    pub paused: bool,    // true = pause, false = unpause
+    pub pause_flags: u8, // resulting GlobalConfig.pause_flags
 }
 
 #[error_code]
 pub enum PvpError {
-    #[msg("Invalid side (must be 0 or 1)")]
+    #[msg("Invalid side (must be 0, 1 or SIDE_ANY on join)")]
     InvalidSide,
     #[msg("Lobby is not open")]
     LobbyNotOpen,
@@ -152,7 +600,7 @@ pub enum PvpError {
     AlreadyFinalized,
     #[msg("Bad remaining accounts length")]
     BadRemainingAccounts,
-    #[msg("Invalid team size (allowed: 1, 2, 5)")]
+    #[msg("Invalid team shape (allowed: 1v1, 2v2, 5v5, 1v10, 2v20, 4v40)")]
     InvalidTeamSize,
     #[msg("Remaining accounts mismatch with team lists")]
     RemainingAccountsMismatch,
@@ -161,13 +609,13 @@ pub enum PvpError {
     #[msg("Lobby is full - must use join_side_final instruction")]
     MustUseFinalJoin,
 
-    #[msg("Wrong VRF request account provided")]
+    #[msg("Wrong randomness account provided")]
     WrongRandomnessAccount,
     
-    #[msg("Invalid randomness data")]
+    #[msg("Malformed randomness account")]
     InvalidRandomnessData,
     
-    #[msg("Randomness not yet fulfilled by Orao VRF")]
+    #[msg("Randomness not yet fulfilled by the provider")]
     RandomnessNotFulfilled,
     
     #[msg("Wrong VRF treasury")]
@@ -175,363 +623,1825 @@ pub enum PvpError {
     
     #[msg("Invalid VRF seed (cannot be zero)")]
     InvalidVrfSeed,
+
+    #[msg("Invalid config value")]
+    InvalidConfigValue,
+
+    #[msg("Platform fee exceeds maximum")]
+    FeeTooHigh,
+
+    #[msg("Invalid pause flags")]
+    InvalidPauseFlags,
+
+    #[msg("Lobby creation is paused")]
+    CreatePaused,
+
+    #[msg("Joining lobbies is paused")]
+    JoinPaused,
+
+    #[msg("Match resolution is paused")]
+    ResolvePaused,
+
+    #[msg("No pending admin transfer for this signer")]
+    NoPendingAdmin,
+
+    #[msg("Invalid council (signers must be unique, at most 10, threshold <= signers)")]
+    InvalidCouncil,
+
+    #[msg("Signer is not a council member")]
+    NotCouncilMember,
+
+    #[msg("Council approval required: pass an approved proposal")]
+    CouncilApprovalRequired,
+
+    #[msg("Proposal does not have enough approvals")]
+    ProposalNotApproved,
+
+    #[msg("Proposal action does not match this instruction")]
+    ProposalActionMismatch,
+
+    #[msg("Proposal already executed")]
+    ProposalAlreadyExecuted,
+
+    #[msg("Signer already approved this proposal")]
+    AlreadyApproved,
+
+    #[msg("Role cannot be granted")]
+    RoleNotGrantable,
+
+    #[msg("Treasury balance too low (must stay rent-exempt)")]
+    InsufficientTreasury,
+
+    #[msg("game / game_mode / arena_type label is too long")]
+    GameFieldTooLong,
+
+    #[msg("Game / mode / arena combination is not registered")]
+    GameNotRegistered,
+
+    #[msg("Game / mode / arena combination is disabled")]
+    GameDisabled,
+
+    #[msg("Team size not allowed for this game")]
+    TeamSizeNotAllowed,
+
+    #[msg("Stake outside the range allowed for this game")]
+    StakeOutOfRange,
+
+    #[msg("Invalid game registry entry")]
+    InvalidGameEntry,
+
+    #[msg("Player is not in this lobby")]
+    NotJoined,
+
+    #[msg("Creator cannot leave their own lobby")]
+    CreatorCannotLeave,
+
+    #[msg("Other players already joined - use refund instead")]
+    LobbyNotEmpty,
+
+    #[msg("VRF timeout has not elapsed yet")]
+    VrfTimeoutNotElapsed,
+
+    #[msg("Randomness already fulfilled - use resolve_match")]
+    RandomnessAlreadyFulfilled,
+
+    #[msg("Lobby has not been stuck long enough for force_refund")]
+    NotStuckLongEnough,
+
+    #[msg("expires_at outside the allowed lobby lifetime")]
+    InvalidExpiry,
+
+    #[msg("Lobby expired")]
+    LobbyExpired,
+
+    #[msg("Lobby has not expired yet")]
+    LobbyNotExpired,
+
+    #[msg("Lobby is not settled (must be Resolved or Refunded)")]
+    LobbyNotSettled,
+
+    #[msg("Active lobby slot exceeds the per-creator limit")]
+    ActiveSlotOutOfRange,
+
+    #[msg("Seats are reserved for the previous match's players")]
+    SeatReserved,
+
+    #[msg("Only players of a resolved match can start a rematch")]
+    RematchNotAllowed,
+
+    #[msg("Resolved lobby is kept for rematch; only its creator can close it before the rematch window ends")]
+    RematchWindowOpen,
+
+    #[msg("Unknown Orao randomness account version")]
+    UnknownRandomnessVersion,
+
+    #[msg("Randomness request seed does not match the lobby's VRF seed")]
+    RandomnessSeedMismatch,
+
+    #[msg("Accounts for the randomness provider are missing")]
+    MissingProviderAccounts,
+
+    #[msg("Switchboard randomness must be committed in the slot before the final join")]
+    StaleRandomnessCommit,
+
+    #[msg("Switchboard randomness account must be controlled by the final joiner")]
+    RandomnessAuthorityNotPayer,
+
+    #[msg("Commit-reveal lobbies require a commitment with every join")]
+    MissingCommitment,
+
+    #[msg("Commitment given for a lobby that is not commit-reveal")]
+    UnexpectedCommitment,
+
+    #[msg("Commit-reveal lobbies are 1v1 only (MAX_COMMIT_REVEAL_PLAYERS)")]
+    CommitRevealLobbyTooLarge,
+
+    #[msg("Lobby is not a commit-reveal lobby")]
+    NotCommitReveal,
+
+    #[msg("Reveal deadline has passed")]
+    RevealWindowClosed,
+
+    #[msg("Waiting for reveals until the reveal deadline")]
+    RevealWindowOpen,
+
+    #[msg("Secret already revealed")]
+    AlreadyRevealed,
+
+    #[msg("Secret does not match the commitment")]
+    CommitmentMismatch,
+
+    #[msg("game / game_mode / arena_type label is empty")]
+    GameFieldEmpty,
+
+    #[msg("This lobby already has a rematch")]
+    AlreadyRematched,
 }
 
 
 // ------------------------------ Accounts ------------------------------
 
 
-// Creates lobby PDA, ActiveLobby PDA, creator joins immediately.
+// Creates the singleton GlobalConfig PDA.
+// Only the program upgrade authority can initialize it (prevents front-running after deploy).
 #[derive(Accounts)]
-#[instruction(lobby_id: u64)]
-pub struct CreateLobby<'info> {
+pub struct InitializeConfig<'info> {
     #[account(
         init,
-        payer = creator,
-        space = Lobby::SIZE,
-        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby_id.to_le_bytes()],
+        payer = authority,
+        space = GlobalConfig::SIZE,
+        seeds = [SEED_CONFIG],
         bump
     )]
-    pub lobby: Account<'info, Lobby>,
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
 
     #[account(
-        init,
-        payer = creator,
-        space = ActiveLobby::SIZE,
-        seeds = [SEED_ACTIVE, creator.key().as_ref()],
-        bump
+        constraint = program.programdata_address()? == Some(program_data.key()) @ PvpError::Unauthorized
     )]
-    pub active: Account<'info, ActiveLobby>,
+    pub program: Program<'info, crate::program::PvpProgram>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key()) @ PvpError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
 
-    #[account(mut)]
-    pub creator: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
-// JoinSideSimple - for non-final joins (when lobby won't be full after this join)
-// Only requires minimal accounts, no VRF accounts needed.
+// Admin-gated config changes (treasury / economics / pause / council / admin handover).
+// `proposal` is only needed for sensitive actions while the council is enabled.
 #[derive(Accounts)]
-pub struct JoinSideSimple<'info> {
+pub struct UpdateConfig<'info> {
     #[account(
         mut,
-        has_one = creator @ PvpError::Unauthorized,
-        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
-        bump
+        seeds = [SEED_CONFIG],
+        bump = config.bump
     )]
-    pub lobby: Account<'info, Lobby>,
+    pub config: Account<'info, GlobalConfig>,
 
-    /// CHECK: Just to satisfy has_one and PDA seeds
-    pub creator: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
 
     #[account(mut)]
-    pub player: Signer<'info>,
+    pub proposal: Option<Account<'info, AdminProposal>>,
+
+    // Caller's RoleGrant (Pauser / FeeManager) when not acting as admin
+    pub role: Option<Account<'info, RoleGrant>>,
+}
+
+// Grants a role to `grantee` (re-activates a previously revoked grant). Admin (or council proposal).
+#[derive(Accounts)]
+#[instruction(role: Role, grantee: Pubkey)]
+pub struct GrantRole<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 
+    // init_if_needed so a revoked grant can be re-activated in place. Re-initialisation is safe:
+    // the PDA is pinned to (role, grantee), only an authorized admin/council call reaches the handler,
+    // and grant_role overwrites every field.
     #[account(
-        mut,
-        seeds = [SEED_ACTIVE, lobby.creator.as_ref()],
+        init_if_needed,
+        payer = authority,
+        space = RoleGrant::SIZE,
+        seeds = [SEED_ROLE, &[role as u8], grantee.as_ref()],
         bump
     )]
-    pub active: Account<'info, ActiveLobby>,
+    pub role_grant: Account<'info, RoleGrant>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
 
     pub system_program: Program<'info, System>,
 }
 
-// JoinSideFull - for the final join (when lobby becomes full after this join)
-// Uses Orao VRF for verifiable randomness
+// Deactivates a RoleGrant. The PDA is kept as an on-chain record of the revocation.
 #[derive(Accounts)]
-#[instruction(side: u8, vrf_seed: [u8; 32])]
-pub struct JoinSideFull<'info> {
+pub struct RevokeRole<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
     #[account(
         mut,
-        has_one = creator @ PvpError::Unauthorized,
-        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
-        bump
+        seeds = [SEED_ROLE, &[role_grant.role as u8], role_grant.grantee.as_ref()],
+        bump = role_grant.bump
     )]
-    pub lobby: Account<'info, Lobby>,
+    pub role_grant: Account<'info, RoleGrant>,
 
-    /// CHECK: Just to satisfy has_one and PDA seeds
-    pub creator: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
 
     #[account(mut)]
-    pub player: Signer<'info>,
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+// Creates the program-owned treasury vault PDA. Point GlobalConfig.treasury at it
+// (update_config) to collect fees on-chain and pay them out via withdraw_treasury.
+#[derive(Accounts)]
+pub struct InitializeTreasuryVault<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump, has_one = admin @ PvpError::Unauthorized)]
+    pub config: Account<'info, GlobalConfig>,
 
     #[account(
-        mut,
-        seeds = [SEED_ACTIVE, lobby.creator.as_ref()],
+        init,
+        payer = admin,
+        space = TreasuryVault::SIZE,
+        seeds = [SEED_TREASURY],
         bump
     )]
-    pub active: Account<'info, ActiveLobby>,
+    pub vault: Account<'info, TreasuryVault>,
 
-    /// Orao VRF randomness request account (PDA derived from seed)
-    /// CHECK: Will be created/validated by Orao VRF program via CPI
-    #[account(
-        mut,
-        seeds = [RANDOMNESS_ACCOUNT_SEED, vrf_seed.as_ref()],
-        bump,
-        seeds::program = orao_solana_vrf::ID
-    )]
-    pub vrf_request: AccountInfo<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
 
-    /// Orao VRF network configuration
-    #[account(
-        mut,
-        seeds = [CONFIG_ACCOUNT_SEED],
-        bump,
-        seeds::program = orao_solana_vrf::ID
+    pub system_program: Program<'info, System>,
+}
+
+// Moves collected fees out of the treasury vault. Treasurer role or admin; council proposal once the council is enabled.
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut, seeds = [SEED_TREASURY], bump = vault.bump)]
+    pub vault: Account<'info, TreasuryVault>,
+
+    /// CHECK: Any destination chosen by the authority
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+
+    pub role: Option<Account<'info, RoleGrant>>,
+}
+
+// Registers or updates a game / mode / arena combination. Admin (or council proposal).
+#[derive(Accounts)]
+#[instruction(game_key: GameKey)]
+pub struct SetGame<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    // init_if_needed so set_game both registers and updates. Same reasoning as GrantRole:
+    // PDA pinned to the key, admin/council only, every field overwritten.
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = GameEntry::SIZE,
+        seeds = [SEED_GAME, &game_key.seed()],
+        bump
     )]
-    pub vrf_config: Account<'info, NetworkState>,
+    pub game_entry: Account<'info, GameEntry>,
 
-    /// Orao VRF treasury (fee collector)
-    /// CHECK: Validated by Orao VRF program
     #[account(mut)]
-    pub vrf_treasury: AccountInfo<'info>,
+    pub authority: Signer<'info>,
 
-    /// Orao VRF program
-    pub vrf_program: Program<'info, OraoVrf>,
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
 
     pub system_program: Program<'info, System>,
 }
 
-// Refund context (Open → Refunded). Closes ActiveLobby (rent back to creator).
+// Removes a registry entry (existing lobbies are unaffected). Admin (or council proposal).
 #[derive(Accounts)]
-pub struct Refund<'info> {
+pub struct RemoveGame<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
     #[account(
         mut,
-        seeds = [SEED_LOBBY, lobby.creator.as_ref(), &lobby.lobby_id.to_le_bytes()],
-        bump
+        seeds = [SEED_GAME, &game_entry.game_key.seed()],
+        bump = game_entry.bump,
+        close = authority
     )]
-    pub lobby: Account<'info, Lobby>,
+    pub game_entry: Account<'info, GameEntry>,
 
-    /// CHECK: Creator account for closing active lobby
     #[account(mut)]
-    pub creator: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
 
-    pub requester: Signer<'info>,
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+}
+
+// Converts a lobby created before the compact layout (LegacyLobby) in place. Admin only:
+// labels aren't unique across registry entries, so the admin picks the entry the lobby maps to.
+#[derive(Accounts)]
+pub struct MigrateLobby<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump, has_one = admin @ PvpError::Unauthorized)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// CHECK: Legacy discriminator and lobby PDA are verified in migrate_lobby
+    #[account(mut, owner = crate::ID)]
+    pub lobby: UncheckedAccount<'info>,
+
+    /// CHECK: GameEntry whose labels match the legacy strings, verified in migrate_lobby
+    pub game_entry: UncheckedAccount<'info>,
+
+    pub admin: Signer<'info>,
+}
 
+// Second step of the admin handover: signed by GlobalConfig.pending_admin.
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
     #[account(
         mut,
-        seeds = [SEED_ACTIVE, lobby.creator.as_ref()],
-        bump,
-        close = creator
+        seeds = [SEED_CONFIG],
+        bump = config.bump
     )]
-    pub active: Account<'info, ActiveLobby>,
+    pub config: Account<'info, GlobalConfig>,
 
-    pub system_program: Program<'info, System>,
-    // remaining_accounts: [all participants: team1..., team2...]
+    pub new_admin: Signer<'info>,
 }
 
-// ResolveMatch - Called to resolve a match and pay winners
-// This is separate from join_side_final so we can handle payouts with remaining_accounts
-// remaining_accounts must include: [treasury, team1..., team2...]
+// Opens a council proposal; the proposer (a council member) approves it implicitly.
 #[derive(Accounts)]
-pub struct ResolveMatch<'info> {
+#[instruction(proposal_id: u64)]
+pub struct CreateProposal<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
     #[account(
-        mut,
-        seeds = [SEED_LOBBY, lobby.creator.as_ref(), &lobby.lobby_id.to_le_bytes()],
+        init,
+        payer = proposer,
+        space = AdminProposal::SIZE,
+        seeds = [SEED_PROPOSAL, &proposal_id.to_le_bytes()],
         bump
     )]
-    pub lobby: Account<'info, Lobby>,
+    pub proposal: Account<'info, AdminProposal>,
 
-    /// CHECK: Creator account for closing active lobby
     #[account(mut)]
-    pub creator: UncheckedAccount<'info>,
+    pub proposer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Adds one council signature to a proposal (one signer per transaction is fine).
+#[derive(Accounts)]
+pub struct ApproveProposal<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 
     #[account(
         mut,
-        seeds = [SEED_ACTIVE, lobby.creator.as_ref()],
-        bump,
-        close = creator
+        seeds = [SEED_PROPOSAL, &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump
     )]
-    pub active: Account<'info, ActiveLobby>,
+    pub proposal: Account<'info, AdminProposal>,
+
+    pub signer: Signer<'info>,
+}
+
+// Cancels or cleans up a proposal (see close_proposal for who may); rent goes back to the proposer.
+#[derive(Accounts)]
+pub struct CloseProposal<'info> {
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 
-    /// Orao VRF randomness request account
-    /// CRITICAL: Must be owned by Orao VRF and match the saved account
-    /// This ensures randomness is provably fair and cannot be manipulated
-    /// CHECK: Verified via owner check (ORAO_VRF_PROGRAM_ID) and constraint check (matches lobby.vrf_seed)
     #[account(
         mut,
-        owner = ORAO_VRF_PROGRAM_ID,
-        constraint = vrf_request.key() == lobby.vrf_request @ PvpError::WrongRandomnessAccount
+        seeds = [SEED_PROPOSAL, &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        has_one = proposer @ PvpError::Unauthorized,
+        close = proposer
     )]
-    pub vrf_request: AccountInfo<'info>,
+    pub proposal: Account<'info, AdminProposal>,
 
-    pub system_program: Program<'info, System>,
-    // remaining_accounts: [admin, team1..., team2...]
+    /// CHECK: Receives the proposal rent, validated by has_one
+    #[account(mut)]
+    pub proposer: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+    // remaining_accounts: further council signers when cancelling someone else's unexecuted proposal
 }
 
-// ------------------------------ Program ------------------------------
+// Creates lobby PDA (header + creator seat; grows on join), ActiveLobby PDA, creator joins immediately.
+#[derive(Accounts)]
+#[instruction(
+    lobby_id: u64,
+    team1_size: u8,
+    team2_size: u8,
+    stake_lamports: u64,
+    side: u8,
+    game_key: GameKey,
+    expires_at: i64,
+    active_slot: u8
+)]
+pub struct CreateLobby<'info> {
+    #[account(
+        init,
+        payer = creator,
+        space = Lobby::space_for_seats(1),
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
 
-#[program]
-pub mod pvp_program {
-    use super::*;
+    #[account(
+        init,
+        payer = creator,
+        space = ActiveLobby::SIZE,
+        seeds = [SEED_ACTIVE, creator.key().as_ref(), active_slot_seed(&active_slot)],
+        bump
+    )]
+    pub active: Account<'info, ActiveLobby>,
 
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 
-    // Creates a lobby, enforces one active lobby per creator, and makes the creator join immediately.
-    // side: 0 (team1) / 1 (team2)
-    pub fn create_lobby(
-        ctx: Context<CreateLobby>,
-        lobby_id: u64,
-        team_size: u8,          // must be 1/2/5
-        stake_lamports: u64,
-        side: u8,               // 0 or 1
-        game: String,           // "PickHigher", etc.
-        game_mode: String,      // "1x3", "3x9", "5x16", etc.
-        arena_type: String,     // "SingleBattle", "DeathMatch"
-        team_size_str: String,  // "1v1", "2v2", "5v5", etc.
-    ) -> Result<()> {
-        require!(ALLOWED_TEAM_SIZES.contains(&team_size), PvpError::InvalidTeamSize);
-        require!(stake_lamports >= MIN_STAKE_LAMPORTS, PvpError::StakeTooSmall);
-        require!(side <= 1, PvpError::InvalidSide);
+    /// GameEntry PDA for game_key
+    /// CHECK: Deserialized and matched against game_key in load_game_entry,
+    /// so an unregistered combination fails with GameNotRegistered instead of an Anchor error
+    pub game_entry: UncheckedAccount<'info>,
 
-        // Initialize lobby state
-        let lobby = &mut ctx.accounts.lobby;
-        lobby.bump               = ctx.bumps.lobby;
-        lobby.lobby_id           = lobby_id;
-        lobby.creator            = ctx.accounts.creator.key();
-        lobby.status             = LobbyStatus::Open;
-        lobby.team_size          = team_size;
-        lobby.stake_lamports     = stake_lamports;
-        lobby.created_at         = Clock::get()?.unix_timestamp;
-        lobby.finalized          = false;
-        lobby.vrf_seed           = [0u8; 32]; // will be set in join_side_final
-        lobby.vrf_request        = Pubkey::default(); // will be set in join_side_final
-        lobby.winner_side        = 0; // not set yet
-        lobby.team1              = Vec::with_capacity(team_size as usize);
-        lobby.team2              = Vec::with_capacity(team_size as usize);
-        // NEW: Store game configuration
-        lobby.game               = game.clone();
-        lobby.game_mode          = game_mode.clone();
-        lobby.arena_type         = arena_type.clone();
-        lobby.team_size_str      = team_size_str.clone();
-
-        // Mark an active lobby for this creator (prevents creating another)
-        let active = &mut ctx.accounts.active;
-        active.bump    = ctx.bumps.active;
-        active.creator = lobby.creator;
-        active.lobby   = lobby.key();
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-        // Creator pays and joins immediately
-        internal_join_side(
-            &ctx.accounts.creator,
-            lobby,
-            ctx.accounts.system_program.to_account_info(),
-            side,
-        )?;
+// Rematch - creates a new lobby from a Resolved one. Sized for every previous participant up front
+// (creator seat + seat reservations), paid by the new creator; a commit-reveal creator seat grows it on join.
+// `previous` records the new lobby, so it can't be rematched twice.
+#[derive(Accounts)]
+#[instruction(lobby_id: u64, side: u8, expires_at: i64, active_slot: u8)]
+pub struct Rematch<'info> {
+    #[account(
+        mut,
+        seeds = [SEED_LOBBY, previous.creator.as_ref(), &previous.lobby_id.to_le_bytes()],
+        bump = previous.bump
+    )]
+    pub previous: Account<'info, Lobby>,
 
-        // Emit lobby created event
-        emit!(LobbyCreated {
-            lobby: lobby.key(),
-            lobby_id,
-            creator: ctx.accounts.creator.key(),
-            stake_lamports,
-            team_size,
-            created_at: lobby.created_at,
-            game,
-            game_mode,
-            arena_type,
-            team_size_str,
-            creator_side: side, // Include which team creator joined
-        });
+    #[account(
+        init,
+        payer = creator,
+        space = Lobby::space_for_seats(previous.team1.len() + previous.team2.len()),
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
 
-        Ok(())
-    }
+    #[account(
+        init,
+        payer = creator,
+        space = ActiveLobby::SIZE,
+        seeds = [SEED_ACTIVE, creator.key().as_ref(), active_slot_seed(&active_slot)],
+        bump
+    )]
+    pub active: Account<'info, ActiveLobby>,
 
-    // A player joins a side (0 or 1) - for non-final joins only.
-    // If this join would fill the lobby, this will error - caller must use join_side_final instead.
-    pub fn join_side(ctx: Context<JoinSideSimple>, side: u8) -> Result<()> {
-        require!(side <= 1, PvpError::InvalidSide);
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 
-        // Must be Open to accept more players
-        let lobby = &mut ctx.accounts.lobby;
-        require!(matches!(lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpenForJoin);
+    /// GameEntry PDA for previous.game_key
+    /// CHECK: Deserialized and matched in load_game_entry (the registry may have changed since)
+    pub game_entry: UncheckedAccount<'info>,
 
-        // Collect stake and add player
-        internal_join_side(
-            &ctx.accounts.player,
-            lobby,
-            ctx.accounts.system_program.to_account_info(),
-            side,
-        )?;
+    // New creator: any participant of the previous lobby
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
 
-        // Check if lobby is now full
-        let full_now =
-            (lobby.team1.len() as u8 == lobby.team_size) &&
-            (lobby.team2.len() as u8 == lobby.team_size);
+// JoinSideSimple - for non-final joins (when lobby won't be full after this join)
+// Only requires minimal accounts, no VRF accounts needed.
+#[derive(Accounts)]
+pub struct JoinSideSimple<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PvpError::Unauthorized,
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
 
-        // Emit player joined event
-        emit!(PlayerJoined {
-            lobby: lobby.key(),
-            player: ctx.accounts.player.key(),
-            side,
-            team1_count: lobby.team1.len() as u8,
-            team2_count: lobby.team2.len() as u8,
-            is_full: full_now,
-            vrf_request: lobby.vrf_request, // Not set yet for non-final joins
-        });
+    /// CHECK: Just to satisfy has_one and PDA seeds
+    pub creator: UncheckedAccount<'info>,
 
-        // If lobby is full, this is an error - should have used join_side_final
-        require!(!full_now, PvpError::MustUseFinalJoin);
+    #[account(mut)]
+    pub player: Signer<'info>,
 
-        Ok(())
-    }
+    #[account(
+        mut,
+        seeds = [SEED_ACTIVE, lobby.creator.as_ref(), active_slot_seed(&lobby.active_slot)],
+        bump
+    )]
+    pub active: Account<'info, ActiveLobby>,
 
-    // Final join - when this join will fill the lobby and trigger VRF request.
-    // IMPORTANT: Caller must provide all Switchboard VRF accounts.
-    pub fn join_side_final(ctx: Context<JoinSideFull>, side: u8, vrf_seed: [u8; 32]) -> Result<()> {
-        msg!("🎯 join_side_final CALLED - side: {}, vrf_seed: {:?}", side, &vrf_seed[..8]);
-        require!(side <= 1, PvpError::InvalidSide);
-        require!(vrf_seed != [0u8; 32], PvpError::InvalidVrfSeed);
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 
-        // Must be Open to accept more players
-        let lobby = &mut ctx.accounts.lobby;
-        require!(matches!(lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpenForJoin);
+    pub system_program: Program<'info, System>,
+}
 
-        // Collect stake and add player
-        internal_join_side(
-            &ctx.accounts.player,
-            lobby,
-            ctx.accounts.system_program.to_account_info(),
-            side,
-        )?;
+// JoinSideFull - for the final join (when lobby becomes full after this join)
+// Requests randomness from GlobalConfig.randomness_provider; the Orao accounts are only needed for Orao
+#[derive(Accounts)]
+pub struct JoinSideFull<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PvpError::Unauthorized,
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
 
-        // Check if lobby is now full
-        let full_now =
-            (lobby.team1.len() as u8 == lobby.team_size) &&
-            (lobby.team2.len() as u8 == lobby.team_size);
+    /// CHECK: Just to satisfy has_one and PDA seeds
+    pub creator: UncheckedAccount<'info>,
 
-        // This instruction should only be called when lobby becomes full
-        if full_now {
-            msg!("🎲 Lobby FULL! Requesting VRF...");
-            // Store VRF seed and request for later resolution
-            lobby.vrf_seed = vrf_seed;
-            lobby.vrf_request = ctx.accounts.vrf_request.key();
+    #[account(mut)]
+    pub player: Signer<'info>,
 
-            // Request randomness from Orao VRF using proper CPI (like in russian-roulette example)
-            let cpi_program = ctx.accounts.vrf_program.to_account_info();
-            let cpi_accounts = orao_solana_vrf::cpi::accounts::RequestV2 {
-                payer: ctx.accounts.player.to_account_info(),
-                network_state: ctx.accounts.vrf_config.to_account_info(),
-                treasury: ctx.accounts.vrf_treasury.to_account_info(),
-                request: ctx.accounts.vrf_request.to_account_info(),
-                system_program: ctx.accounts.system_program.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-            orao_solana_vrf::cpi::request_v2(cpi_ctx, vrf_seed)?;
-            
-            msg!("VRF request sent to Orao network!");
-            
-            // Move to Pending - waiting for Orao oracles to fulfill (sub-second!)
-            lobby.status = LobbyStatus::Pending;
-            
-            msg!("Lobby full! VRF requested. Waiting for fulfillment, then call resolve_match.");
-        }
+    #[account(
+        mut,
+        seeds = [SEED_ACTIVE, lobby.creator.as_ref(), active_slot_seed(&lobby.active_slot)],
+        bump
+    )]
+    pub active: Account<'info, ActiveLobby>,
 
-        // Emit player joined event AFTER VRF request (so vrf_request is included)
-        emit!(PlayerJoined {
-            lobby: lobby.key(),
-            player: ctx.accounts.player.key(),
-            side,
-            team1_count: lobby.team1.len() as u8,
+    /// Provider randomness account: Orao request PDA of derive_vrf_seed(lobby, lobby_id, vrf_nonce),
+    /// or a Switchboard randomness account committed in the previous slot (None for commit-reveal lobbies)
+    /// CHECK: Checked in request_randomness; the Orao request is created by Orao VRF program via CPI
+    #[account(mut)]
+    pub vrf_request: Option<UncheckedAccount<'info>>,
+
+    /// Orao VRF network configuration
+    #[account(
+        mut,
+        seeds = [CONFIG_ACCOUNT_SEED],
+        bump,
+        seeds::program = orao_solana_vrf::ID
+    )]
+    pub vrf_config: Option<Account<'info, NetworkState>>,
+
+    /// Orao VRF treasury (fee collector)
+    /// CHECK: Validated by Orao VRF program
+    #[account(mut)]
+    pub vrf_treasury: Option<UncheckedAccount<'info>>,
+
+    /// Orao VRF program
+    pub vrf_program: Option<Program<'info, OraoVrf>>,
+
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// LeaveLobby - a non-creator player leaves an Open lobby and gets stake + seat rent back
+#[derive(Accounts)]
+pub struct LeaveLobby<'info> {
+    #[account(
+        mut,
+        seeds = [SEED_LOBBY, lobby.creator.as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+// CancelLobby - creator cancels an Open lobby nobody else joined.
+// Closes Lobby and ActiveLobby; stake and all rent go back to the creator.
+#[derive(Accounts)]
+pub struct CancelLobby<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PvpError::Unauthorized,
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump,
+        close = creator
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(
+        mut,
+        seeds = [SEED_ACTIVE, creator.key().as_ref(), active_slot_seed(&lobby.active_slot)],
+        bump,
+        close = creator
+    )]
+    pub active: Account<'info, ActiveLobby>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+// QuickPlay - join the first matching Open lobby among remaining_accounts, or create one.
+// VRF accounts are only needed to take a candidate's last seat; new_lobby / new_active only
+// for the fallback create (PDAs of [lobby, player, lobby_id] and the player's active slot).
+#[derive(Accounts)]
+pub struct QuickPlay<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// GameEntry PDA for game_key (fallback create)
+    /// CHECK: Deserialized and matched against game_key in load_game_entry
+    pub game_entry: UncheckedAccount<'info>,
+
+    /// Fallback lobby PDA
+    /// CHECK: Address verified and account created in quick_play only when no candidate matches
+    #[account(mut)]
+    pub new_lobby: UncheckedAccount<'info>,
+
+    /// Fallback ActiveLobby PDA
+    /// CHECK: Address verified and account created in quick_play only when no candidate matches
+    #[account(mut)]
+    pub new_active: UncheckedAccount<'info>,
+
+    /// Provider randomness account for the candidate expected to fill (see JoinSideFull)
+    /// CHECK: Filling candidates it can't serve are skipped (randomness_request_ok)
+    #[account(mut)]
+    pub vrf_request: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_ACCOUNT_SEED],
+        bump,
+        seeds::program = orao_solana_vrf::ID
+    )]
+    pub vrf_config: Option<Account<'info, NetworkState>>,
+
+    /// CHECK: Validated by Orao VRF program
+    #[account(mut)]
+    pub vrf_treasury: Option<UncheckedAccount<'info>>,
+
+    pub vrf_program: Option<Program<'info, OraoVrf>>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: candidate lobbies (writable), tried in order
+}
+
+// Refund context (Open → Refunded). Closes ActiveLobby (rent back to creator).
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(
+        mut,
+        seeds = [SEED_LOBBY, lobby.creator.as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    /// CHECK: Creator account for closing active lobby
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+
+    pub requester: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [SEED_ACTIVE, lobby.creator.as_ref(), active_slot_seed(&lobby.active_slot)],
+        bump,
+        close = creator
+    )]
+    pub active: Account<'info, ActiveLobby>,
+
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    // Approved ForceRefund proposal (force_refund by admin while the council is enabled)
+    #[account(mut)]
+    pub proposal: Option<Account<'info, AdminProposal>>,
+
+    // Requester's RefundOperator grant
+    pub role: Option<Account<'info, RoleGrant>>,
+
+    /// Randomness account saved on the lobby (force_refund of a Pending lobby)
+    /// CHECK: Only read to make sure randomness is NOT fulfilled
+    #[account(constraint = vrf_request.key() == lobby.vrf_request @ PvpError::WrongRandomnessAccount)]
+    pub vrf_request: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: [all participants: team1..., team2...]
+}
+
+// TimeoutRefund - permissionless refund of a Pending lobby whose VRF request timed out (Pending → Refunded)
+#[derive(Accounts)]
+pub struct TimeoutRefund<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PvpError::Unauthorized,
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    /// CHECK: Creator account for closing active lobby
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [SEED_ACTIVE, creator.key().as_ref(), active_slot_seed(&lobby.active_slot)],
+        bump,
+        close = creator
+    )]
+    pub active: Account<'info, ActiveLobby>,
+
+    /// Randomness account saved on the lobby
+    /// CHECK: Only read to make sure randomness is NOT fulfilled (it may not even exist)
+    #[account(constraint = vrf_request.key() == lobby.vrf_request @ PvpError::WrongRandomnessAccount)]
+    pub vrf_request: AccountInfo<'info>,
+
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    // Any participant or cranker (receives the cranker reward)
+    #[account(mut)]
+    pub requester: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: [all participants: team1..., team2...]
+}
+
+// ExpireRefund - permissionless refund of an Open lobby past its expires_at (Open → Refunded)
+#[derive(Accounts)]
+pub struct ExpireRefund<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PvpError::Unauthorized,
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    /// CHECK: Creator account for closing active lobby
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [SEED_ACTIVE, creator.key().as_ref(), active_slot_seed(&lobby.active_slot)],
+        bump,
+        close = creator
+    )]
+    pub active: Account<'info, ActiveLobby>,
+
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    // Any participant or keeper (receives the cranker reward)
+    #[account(mut)]
+    pub requester: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: [all participants: team1..., team2...]
+}
+
+// Reveal - a player of a full commit-reveal lobby reveals the secret behind their commitment
+#[derive(Accounts)]
+pub struct Reveal<'info> {
+    #[account(
+        mut,
+        seeds = [SEED_LOBBY, lobby.creator.as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    pub player: Signer<'info>,
+}
+
+// ResolveMatch - Called to resolve a match and pay winners
+// This is separate from join_side_final so we can handle payouts with remaining_accounts
+// remaining_accounts must include: [treasury, team1..., team2...]
+#[derive(Accounts)]
+pub struct ResolveMatch<'info> {
+    #[account(
+        mut,
+        seeds = [SEED_LOBBY, lobby.creator.as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    /// CHECK: Creator account for closing active lobby
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [SEED_ACTIVE, lobby.creator.as_ref(), active_slot_seed(&lobby.active_slot)],
+        bump,
+        close = creator
+    )]
+    pub active: Account<'info, ActiveLobby>,
+
+    /// Randomness account of the lobby's provider (None for commit-reveal lobbies)
+    /// CRITICAL: Must be owned by the provider program and match the saved account
+    /// This ensures randomness is provably fair and cannot be manipulated
+    /// CHECK: Verified via constraint check (matches lobby.vrf_request) and the provider owner / seed checks in oracle_outcome
+    #[account(
+        mut,
+        constraint = vrf_request.key() == lobby.vrf_request @ PvpError::WrongRandomnessAccount
+    )]
+    pub vrf_request: Option<UncheckedAccount<'info>>,
+
+    #[account(seeds = [SEED_CONFIG], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    // Whoever resolves (receives the cranker reward)
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: [treasury (config.treasury), team1..., team2...]
+}
+
+// CloseLobby - closes a settled Lobby PDA (Resolved / Refunded). Permissionless, except that a Resolved
+// lobby can only be closed by its creator during the rematch window. The creator may later create a new
+// lobby with the same lobby_id at the same address; events carry created_slot to tell them apart.
+#[derive(Accounts)]
+pub struct CloseLobby<'info> {
+    #[account(
+        mut,
+        has_one = creator @ PvpError::Unauthorized,
+        seeds = [SEED_LOBBY, creator.key().as_ref(), &lobby.lobby_id.to_le_bytes()],
+        bump,
+        close = creator
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    /// CHECK: Receives the remaining rent
+    #[account(mut)]
+    pub creator: UncheckedAccount<'info>,
+
+    pub closer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    // remaining_accounts: [all participants: team1..., team2...] (seat rent back to each joiner)
+}
+
+// ------------------------------ Program ------------------------------
+
+#[program]
+pub mod pvp_program {
+    use super::*;
+
+
+    // One-time setup of the GlobalConfig PDA (admin + treasury).
+    pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey, treasury: Pubkey) -> Result<()> {
+        require!(admin != Pubkey::default(), PvpError::InvalidConfigValue);
+        require!(treasury != Pubkey::default(), PvpError::InvalidConfigValue);
+
+        let config = &mut ctx.accounts.config;
+        config.bump               = ctx.bumps.config;
+        config.admin              = admin;
+        config.treasury           = treasury;
+        config.fee_bps            = DEFAULT_PLATFORM_FEE_BPS;
+        config.min_stake_lamports = DEFAULT_MIN_STAKE_LAMPORTS;
+        config.refund_lock_secs   = DEFAULT_REFUND_LOCK_SECS;
+        config.vrf_timeout_secs   = DEFAULT_VRF_TIMEOUT_SECS;
+        config.force_refund_min_stuck_secs = DEFAULT_FORCE_REFUND_MIN_STUCK_SECS;
+        config.min_lobby_ttl_secs = DEFAULT_MIN_LOBBY_TTL_SECS;
+        config.max_lobby_ttl_secs = DEFAULT_MAX_LOBBY_TTL_SECS;
+        config.max_active_lobbies = DEFAULT_MAX_ACTIVE_LOBBIES;
+        config.randomness_provider = RandomnessProvider::Orao;
+        config.crank_reward_bps   = DEFAULT_CRANK_REWARD_BPS;
+        config.max_crank_reward_lamports = DEFAULT_MAX_CRANK_REWARD_LAMPORTS;
+        config.pause_flags        = 0;
+        config.pending_admin      = Pubkey::default();
+        config.council_threshold  = 0;
+        config.council_since      = 0;
+        config.council            = Vec::new();
+
+        emit!(ConfigUpdated { admin, treasury });
+        Ok(())
+    }
+
+    // Rotates the treasury without redeploying. Admin (or council proposal).
+    pub fn update_config(ctx: Context<UpdateConfig>, new_treasury: Pubkey) -> Result<()> {
+        require!(new_treasury != Pubkey::default(), PvpError::InvalidConfigValue);
+
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::UpdateTreasury { treasury: new_treasury },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::UpdateTreasury });
+
+        let config = &mut ctx.accounts.config;
+        config.treasury = new_treasury;
+
+        emit!(ConfigUpdated { admin: config.admin, treasury: new_treasury });
+        Ok(())
+    }
+
+    // Changes fee / min stake / refund lock / cranker reward at runtime. FeeManager role or admin (or council proposal).
+    // New values apply to every subsequent create_lobby, refund and resolve_match.
+    pub fn update_economics(
+        ctx: Context<UpdateConfig>,
+        fee_bps: u16,
+        min_stake_lamports: u64,
+        refund_lock_secs: i64,
+        crank_reward_bps: u16,          // share of the pot paid to the cranker (0 = disabled)
+        max_crank_reward_lamports: u64, // per-call cap
+    ) -> Result<()> {
+        require!(fee_bps <= MAX_PLATFORM_FEE_BPS, PvpError::FeeTooHigh);
+        require!(min_stake_lamports > 0, PvpError::InvalidConfigValue);
+        require!(
+            (0..=MAX_REFUND_LOCK_SECS).contains(&refund_lock_secs),
+            PvpError::InvalidConfigValue
+        );
+        require!(crank_reward_bps <= MAX_CRANK_REWARD_BPS, PvpError::InvalidConfigValue);
+        require!(max_crank_reward_lamports <= MAX_CRANK_REWARD_LAMPORTS, PvpError::InvalidConfigValue);
+
+        let authority = ctx.accounts.authority.key();
+        let role = if has_role(&ctx.accounts.config, ctx.accounts.role.as_ref(), &authority, Role::FeeManager) {
+            Role::FeeManager
+        } else {
+            authorize_admin_action(
+                &ctx.accounts.config,
+                &authority,
+                ctx.accounts.proposal.as_mut(),
+                &AdminAction::UpdateEconomics {
+                    fee_bps,
+                    min_stake_lamports,
+                    refund_lock_secs,
+                    crank_reward_bps,
+                    max_crank_reward_lamports,
+                },
+            )?
+        };
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::UpdateEconomics });
+
+        let config = &mut ctx.accounts.config;
+        let event = EconomicsUpdated {
+            old_fee_bps: config.fee_bps,
+            new_fee_bps: fee_bps,
+            old_min_stake_lamports: config.min_stake_lamports,
+            new_min_stake_lamports: min_stake_lamports,
+            old_refund_lock_secs: config.refund_lock_secs,
+            new_refund_lock_secs: refund_lock_secs,
+            old_crank_reward_bps: config.crank_reward_bps,
+            new_crank_reward_bps: crank_reward_bps,
+            old_max_crank_reward_lamports: config.max_crank_reward_lamports,
+            new_max_crank_reward_lamports: max_crank_reward_lamports,
+        };
+        config.fee_bps                   = fee_bps;
+        config.min_stake_lamports        = min_stake_lamports;
+        config.refund_lock_secs          = refund_lock_secs;
+        config.crank_reward_bps          = crank_reward_bps;
+        config.max_crank_reward_lamports = max_crank_reward_lamports;
+
+        emit!(event);
+        Ok(())
+    }
+
+    // Recovery timeouts. Admin (or council proposal).
+    // vrf_timeout_secs: Pending time without fulfilled randomness before timeout_refund opens.
+    // force_refund_min_stuck_secs: how long a lobby must be stuck before force_refund accepts it.
+    // min / max_lobby_ttl_secs: allowed range for expires_at - now on create_lobby.
+    pub fn update_timeouts(
+        ctx: Context<UpdateConfig>,
+        vrf_timeout_secs: i64,
+        force_refund_min_stuck_secs: i64,
+        min_lobby_ttl_secs: i64,
+        max_lobby_ttl_secs: i64,
+    ) -> Result<()> {
+        require!(
+            (MIN_VRF_TIMEOUT_SECS..=MAX_VRF_TIMEOUT_SECS).contains(&vrf_timeout_secs),
+            PvpError::InvalidConfigValue
+        );
+        require!(
+            (0..=MAX_FORCE_REFUND_MIN_STUCK_SECS).contains(&force_refund_min_stuck_secs),
+            PvpError::InvalidConfigValue
+        );
+        require!(
+            min_lobby_ttl_secs > 0 && min_lobby_ttl_secs <= max_lobby_ttl_secs && max_lobby_ttl_secs <= MAX_LOBBY_TTL_SECS,
+            PvpError::InvalidConfigValue
+        );
+
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::UpdateTimeouts {
+                vrf_timeout_secs,
+                force_refund_min_stuck_secs,
+                min_lobby_ttl_secs,
+                max_lobby_ttl_secs,
+            },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::UpdateTimeouts });
+
+        let config = &mut ctx.accounts.config;
+        let event = TimeoutsUpdated {
+            old_vrf_timeout_secs: config.vrf_timeout_secs,
+            new_vrf_timeout_secs: vrf_timeout_secs,
+            old_force_refund_min_stuck_secs: config.force_refund_min_stuck_secs,
+            new_force_refund_min_stuck_secs: force_refund_min_stuck_secs,
+            old_min_lobby_ttl_secs: config.min_lobby_ttl_secs,
+            new_min_lobby_ttl_secs: min_lobby_ttl_secs,
+            old_max_lobby_ttl_secs: config.max_lobby_ttl_secs,
+            new_max_lobby_ttl_secs: max_lobby_ttl_secs,
+        };
+        config.vrf_timeout_secs            = vrf_timeout_secs;
+        config.force_refund_min_stuck_secs = force_refund_min_stuck_secs;
+        config.min_lobby_ttl_secs          = min_lobby_ttl_secs;
+        config.max_lobby_ttl_secs          = max_lobby_ttl_secs;
+
+        emit!(event);
+        Ok(())
+    }
+
+    // How many lobbies a single creator may host at once (ActiveLobby slots 0..max). Admin (or council proposal).
+    // Lowering it doesn't touch running lobbies; their slots just can't be reused until back in range.
+    pub fn set_lobby_limit(ctx: Context<UpdateConfig>, max_active_lobbies: u8) -> Result<()> {
+        require!(
+            (1..=MAX_ACTIVE_LOBBIES).contains(&max_active_lobbies),
+            PvpError::InvalidConfigValue
+        );
+
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::SetLobbyLimit { max_active_lobbies },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::SetLobbyLimit });
+
+        let config = &mut ctx.accounts.config;
+        let event = LobbyLimitUpdated {
+            old_max_active_lobbies: config.max_active_lobbies,
+            new_max_active_lobbies: max_active_lobbies,
+        };
+        config.max_active_lobbies = max_active_lobbies;
+
+        emit!(event);
+        Ok(())
+    }
+
+    // Randomness provider for lobbies that fill from now on (e.g. fail over to Switchboard during an Orao outage).
+    // Pending lobbies keep the provider they requested from. Admin (or council proposal).
+    pub fn set_randomness_provider(ctx: Context<UpdateConfig>, provider: RandomnessProvider) -> Result<()> {
+        // Commit-reveal is a per-lobby mode picked on create, not an oracle to fail over to
+        require!(provider != RandomnessProvider::CommitReveal, PvpError::InvalidConfigValue);
+
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::SetRandomnessProvider { provider },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::SetRandomnessProvider });
+
+        let config = &mut ctx.accounts.config;
+        let event = RandomnessProviderUpdated {
+            old_provider: config.randomness_provider,
+            new_provider: provider,
+        };
+        config.randomness_provider = provider;
+
+        emit!(event);
+        Ok(())
+    }
+
+    // Emergency circuit breaker. `flags` is any combination of PAUSE_CREATE / PAUSE_JOIN / PAUSE_RESOLVE;
+    // `paused` sets (true) or clears (false) those switches.
+    // Admin or Pauser role (pause only); never routed through the council so it stays instant.
+    pub fn set_pause(ctx: Context<UpdateConfig>, flags: u8, paused: bool) -> Result<()> {
+        require!(flags != 0 && flags & !PAUSE_ALL == 0, PvpError::InvalidPauseFlags);
+
+        let authority = ctx.accounts.authority.key();
+        let role = if authority == ctx.accounts.config.admin {
+            Role::Admin
+        } else if paused && has_role(&ctx.accounts.config, ctx.accounts.role.as_ref(), &authority, Role::Pauser) {
+            Role::Pauser
+        } else {
+            return err!(PvpError::Unauthorized);
+        };
+        let op = if paused { PrivilegedOp::Pause } else { PrivilegedOp::Unpause };
+        emit!(PrivilegeUsed { authority, role, op });
+
+        let config = &mut ctx.accounts.config;
+        if paused {
+            config.pause_flags |= flags;
+        } else {
+            config.pause_flags &= !flags;
+        }
+
+        emit!(PauseUpdated {
+            authority,
+            flags,
+            paused,
+            pause_flags: config.pause_flags,
+        });
+        Ok(())
+    }
+
+    // Step 1 of the admin handover: nominate `new_admin` (Pubkey::default() cancels).
+    // Admin (or council proposal). Nothing changes until the nominee calls accept_admin.
+    pub fn propose_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::ProposeAdmin { new_admin },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::ProposeAdmin });
+
+        let config = &mut ctx.accounts.config;
+        config.pending_admin = new_admin;
+
+        emit!(AdminTransferProposed { admin: config.admin, pending_admin: new_admin });
+        Ok(())
+    }
+
+    // Step 2 of the admin handover: the nominee proves control of the key.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let signer = ctx.accounts.new_admin.key();
+        require!(
+            config.pending_admin != Pubkey::default() && config.pending_admin == signer,
+            PvpError::NoPendingAdmin
+        );
+
+        let old_admin = config.admin;
+        config.admin = signer;
+        config.pending_admin = Pubkey::default();
+
+        emit!(AdminTransferred { old_admin, new_admin: signer });
+        Ok(())
+    }
+
+    // Sets the M-of-N council. threshold = 0 disables it (admin key acts alone again).
+    // Admin while disabled; once enabled, changing it needs a council proposal itself.
+    pub fn set_council(ctx: Context<UpdateConfig>, signers: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        require!(signers.len() <= MAX_COUNCIL_SIGNERS, PvpError::InvalidCouncil);
+        require!((threshold as usize) <= signers.len(), PvpError::InvalidCouncil);
+        for (i, k) in signers.iter().enumerate() {
+            require!(*k != Pubkey::default() && !signers[..i].contains(k), PvpError::InvalidCouncil);
+        }
+
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::SetCouncil { signers: signers.clone(), threshold },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::SetCouncil });
+
+        let config = &mut ctx.accounts.config;
+        if !config.council_enabled() && threshold > 0 {
+            // Grants the admin made alone must not outlive the switch to M-of-N
+            config.council_since = Clock::get()?.unix_timestamp;
+        }
+        config.council = signers.clone();
+        config.council_threshold = threshold;
+
+        emit!(CouncilUpdated { signers, threshold });
+        Ok(())
+    }
+
+    // Grants `role` to `grantee`. Admin (or council proposal).
+    pub fn grant_role(ctx: Context<GrantRole>, role: Role, grantee: Pubkey) -> Result<()> {
+        require!(role.is_grantable(), PvpError::RoleNotGrantable);
+        require!(grantee != Pubkey::default(), PvpError::InvalidConfigValue);
+
+        let authority = ctx.accounts.authority.key();
+        let used = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::GrantRole { role, grantee },
+        )?;
+        emit!(PrivilegeUsed { authority, role: used, op: PrivilegedOp::GrantRole });
+
+        let grant = &mut ctx.accounts.role_grant;
+        grant.bump       = ctx.bumps.role_grant;
+        grant.role       = role;
+        grant.grantee    = grantee;
+        grant.active     = true;
+        grant.granted_by = authority;
+        grant.granted_at = Clock::get()?.unix_timestamp;
+        grant.revoked_at = 0;
+
+        emit!(RoleGranted { role, grantee, granted_by: authority });
+        Ok(())
+    }
+
+    // Revokes a role grant. Admin (or council proposal).
+    pub fn revoke_role(ctx: Context<RevokeRole>) -> Result<()> {
+        let role = ctx.accounts.role_grant.role;
+        let grantee = ctx.accounts.role_grant.grantee;
+
+        let authority = ctx.accounts.authority.key();
+        let used = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::RevokeRole { role, grantee },
+        )?;
+        emit!(PrivilegeUsed { authority, role: used, op: PrivilegedOp::RevokeRole });
+
+        let grant = &mut ctx.accounts.role_grant;
+        grant.active     = false;
+        grant.revoked_at = Clock::get()?.unix_timestamp;
+
+        emit!(RoleRevoked { role, grantee, revoked_by: authority });
+        Ok(())
+    }
+
+    // Creates the treasury vault PDA. Admin only.
+    pub fn initialize_treasury_vault(ctx: Context<InitializeTreasuryVault>) -> Result<()> {
+        ctx.accounts.vault.bump = ctx.bumps.vault;
+        Ok(())
+    }
+
+    // Withdraws collected fees from the treasury vault. The vault always stays rent-exempt.
+    // The destination is free, so with the council enabled every withdrawal needs a proposal:
+    // a Treasurer alone could otherwise drain the vault anywhere, bypassing M-of-N.
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        let destination = ctx.accounts.destination.key();
+        let authority = ctx.accounts.authority.key();
+        let role = if !ctx.accounts.config.council_enabled()
+            && has_role(&ctx.accounts.config, ctx.accounts.role.as_ref(), &authority, Role::Treasurer) {
+            Role::Treasurer
+        } else {
+            authorize_admin_action(
+                &ctx.accounts.config,
+                &authority,
+                ctx.accounts.proposal.as_mut(),
+                &AdminAction::WithdrawTreasury { destination, amount },
+            )?
+        };
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::WithdrawTreasury });
+
+        let vault_ai = ctx.accounts.vault.to_account_info();
+        let rent_min = Rent::get()?.minimum_balance(vault_ai.data_len());
+        require!(
+            vault_ai.lamports().checked_sub(amount).is_some_and(|left| left >= rent_min),
+            PvpError::InsufficientTreasury
+        );
+
+        **vault_ai.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.destination.try_borrow_mut_lamports()? += amount;
+
+        emit!(TreasuryWithdrawn { destination, amount, authority });
+        Ok(())
+    }
+
+    // Registers (or updates) a supported game / mode / arena combination with its
+    // allowed team sizes and stake range. Admin (or council proposal).
+    pub fn set_game(
+        ctx: Context<SetGame>,
+        game_key: GameKey,
+        game: String,
+        game_mode: String,
+        arena_type: String,
+        team_shapes: Vec<TeamShape>,
+        min_stake_lamports: u64,
+        max_stake_lamports: u64,
+        enabled: bool,
+    ) -> Result<()> {
+        require!(
+            !game.is_empty() && !game_mode.is_empty() && !arena_type.is_empty(),
+            PvpError::GameFieldEmpty
+        );
+        require!(
+            game.len() <= MAX_GAME_LEN && game_mode.len() <= MAX_GAME_MODE_LEN && arena_type.len() <= MAX_ARENA_TYPE_LEN,
+            PvpError::GameFieldTooLong
+        );
+        require!(
+            !team_shapes.is_empty()
+                && team_shapes.len() <= MAX_GAME_SHAPES
+                && team_shapes.iter().all(|t| t.is_allowed()),
+            PvpError::InvalidGameEntry
+        );
+        require!(min_stake_lamports <= max_stake_lamports, PvpError::InvalidGameEntry);
+
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::SetGame {
+                game_key,
+                game: game.clone(),
+                game_mode: game_mode.clone(),
+                arena_type: arena_type.clone(),
+                team_shapes: team_shapes.clone(),
+                min_stake_lamports,
+                max_stake_lamports,
+                enabled,
+            },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::SetGame });
+
+        let entry = &mut ctx.accounts.game_entry;
+        entry.bump               = ctx.bumps.game_entry;
+        entry.game_key           = game_key;
+        entry.game               = game.clone();
+        entry.game_mode          = game_mode.clone();
+        entry.arena_type         = arena_type.clone();
+        entry.team_shapes        = team_shapes.clone();
+        entry.min_stake_lamports = min_stake_lamports;
+        entry.max_stake_lamports = max_stake_lamports;
+        entry.enabled            = enabled;
+
+        emit!(GameEntryUpdated {
+            game_entry: entry.key(),
+            game_key,
+            game,
+            game_mode,
+            arena_type,
+            team_shapes,
+            min_stake_lamports,
+            max_stake_lamports,
+            enabled,
+        });
+        Ok(())
+    }
+
+    // Removes a registry entry and returns its rent. Admin (or council proposal).
+    pub fn remove_game(ctx: Context<RemoveGame>) -> Result<()> {
+        let game_entry = ctx.accounts.game_entry.key();
+        let authority = ctx.accounts.authority.key();
+        let role = authorize_admin_action(
+            &ctx.accounts.config,
+            &authority,
+            ctx.accounts.proposal.as_mut(),
+            &AdminAction::RemoveGame { game_entry },
+        )?;
+        emit!(PrivilegeUsed { authority, role, op: PrivilegedOp::RemoveGame });
+
+        emit!(GameEntryRemoved { game_entry });
+        Ok(())
+    }
+
+    // Rewrites a LegacyLobby (four String fields) into the compact Lobby layout so every
+    // instruction can settle it. The GameEntry whose labels match the legacy strings supplies the key;
+    // when several entries share those labels the admin chooses which one by passing it.
+    pub fn migrate_lobby(ctx: Context<MigrateLobby>) -> Result<()> {
+        let lobby_ai = ctx.accounts.lobby.to_account_info();
+        let legacy = {
+            let data = lobby_ai.try_borrow_data()?;
+            LegacyLobby::try_deserialize(&mut &data[..])?
+        };
+        let expected = Pubkey::create_program_address(
+            &[SEED_LOBBY, legacy.creator.as_ref(), &legacy.lobby_id.to_le_bytes(), &[legacy.bump]],
+            &crate::ID,
+        ).map_err(|_| error!(PvpError::Unauthorized))?;
+        require!(expected == lobby_ai.key(), PvpError::Unauthorized);
+
+        let entry = {
+            let entry_ai = &ctx.accounts.game_entry;
+            require!(entry_ai.owner == &crate::ID, PvpError::GameNotRegistered);
+            let data = entry_ai.try_borrow_data()?;
+            GameEntry::try_deserialize(&mut &data[..]).map_err(|_| error!(PvpError::GameNotRegistered))?
+        };
+        require!(
+            entry.game == legacy.game && entry.game_mode == legacy.game_mode && entry.arena_type == legacy.arena_type,
+            PvpError::GameNotRegistered
+        );
+
+        let lobby = Lobby {
+            bump: legacy.bump,
+            lobby_id: legacy.lobby_id,
+            creator: legacy.creator,
+            status: legacy.status,
+            team1_size: legacy.team_size,
+            stake_lamports: legacy.stake_lamports,
+            created_at: legacy.created_at,
+            finalized: legacy.finalized,
+            vrf_seed: legacy.vrf_seed,
+            vrf_request: legacy.vrf_request,
+            winner_side: legacy.winner_side,
+            game_key: entry.game_key,
+            team2_size: legacy.team_size,
+            // Legacy lobbies don't know when they filled; start the VRF timeout now
+            pending_since: if legacy.status == LobbyStatus::Pending { Clock::get()?.unix_timestamp } else { 0 },
+            expires_at: legacy.created_at.saturating_add(DEFAULT_MAX_LOBBY_TTL_SECS),
+            active_slot: 0, // legacy lobbies hold the original ["active", creator] PDA
+            reserved_until: 0,
+            randomness_provider: RandomnessProvider::Orao, // the only provider before providers were pluggable
+            settled_at: 0, // unknown: a migrated Resolved lobby can be closed right away
+            seat_rent_prepaid: true,
+            created_slot: 0, // unknown for legacy lobbies
+            rematch_lobby: Pubkey::default(),
+            randomness_side: 0,
+            team1: legacy.team1,
+            team2: legacy.team2,
+            reserved: Vec::new(),
+            commitments: Vec::new(),
+        };
+
+        // Keep room for every seat: the creator paid the legacy full-size account, so nobody pays seat rent
+        // on join (or gets it back on leave / close). Shrinking keeps every lamport (stakes + rent) in the PDA.
+        lobby_ai.realloc(Lobby::space_for_seats(legacy.team_size as usize * 2), false)?;
+        {
+            let mut data = lobby_ai.try_borrow_mut_data()?;
+            lobby.try_serialize(&mut &mut data[..])?;
+        }
+
+        emit!(LobbyMigrated { lobby: lobby_ai.key(), game_key: entry.game_key });
+        Ok(())
+    }
+
+    // Opens a proposal for a sensitive action. Council members only.
+    pub fn create_proposal(ctx: Context<CreateProposal>, proposal_id: u64, action: AdminAction) -> Result<()> {
+        let proposer = ctx.accounts.proposer.key();
+        require!(ctx.accounts.config.is_council_member(&proposer), PvpError::NotCouncilMember);
+        if let AdminAction::SetCouncil { signers, .. } = &action {
+            require!(signers.len() <= MAX_COUNCIL_SIGNERS, PvpError::InvalidCouncil);
+        }
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.bump        = ctx.bumps.proposal;
+        proposal.proposal_id = proposal_id;
+        proposal.proposer    = proposer;
+        proposal.action      = action.clone();
+        proposal.approvals   = vec![proposer];
+        proposal.created_at  = Clock::get()?.unix_timestamp;
+        proposal.executed    = false;
+
+        emit!(ProposalCreated {
+            proposal: proposal.key(),
+            proposal_id,
+            proposer,
+            action,
+        });
+        Ok(())
+    }
+
+    // Adds the signer's approval. Approvals from removed council members are dropped.
+    pub fn approve_proposal(ctx: Context<ApproveProposal>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let signer = ctx.accounts.signer.key();
+        require!(config.is_council_member(&signer), PvpError::NotCouncilMember);
+
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, PvpError::ProposalAlreadyExecuted);
+        proposal.approvals.retain(|k| config.is_council_member(k));
+        require!(!proposal.approvals.contains(&signer), PvpError::AlreadyApproved);
+        proposal.approvals.push(signer);
+
+        emit!(ProposalApproved {
+            proposal: proposal.key(),
+            signer,
+            approvals: proposal.approvals.len() as u8,
+            threshold: config.council_threshold,
+        });
+        Ok(())
+    }
+
+    // Cancels an unexecuted proposal: its proposer, or council_threshold council members signing together
+    // (authority + signer remaining_accounts). The admin alone can't, so a compromised admin key can't kill
+    // a proposal that rotates it out. Executed proposals can be cleaned up by the proposer or admin.
+    pub fn close_proposal(ctx: Context<CloseProposal>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let config = &ctx.accounts.config;
+        let proposal = &ctx.accounts.proposal;
+        if proposal.executed {
+            require!(authority == proposal.proposer || authority == config.admin, PvpError::Unauthorized);
+        } else if authority != proposal.proposer {
+            let mut signers: Vec<Pubkey> = ctx.remaining_accounts.iter()
+                .filter(|a| a.is_signer)
+                .map(|a| a.key())
+                .chain(std::iter::once(authority))
+                .filter(|k| config.is_council_member(k))
+                .collect();
+            signers.sort();
+            signers.dedup();
+            require!(
+                config.council_enabled() && signers.len() >= config.council_threshold as usize,
+                PvpError::Unauthorized
+            );
+        }
+
+        emit!(ProposalClosed {
+            proposal: ctx.accounts.proposal.key(),
+            executed: ctx.accounts.proposal.executed,
+        });
+        Ok(())
+    }
+
+    // Creates a lobby in one of the creator's ActiveLobby slots (at most config.max_active_lobbies at once)
+    // and makes the creator join immediately.
+    // side: 0 (team1) / 1 (team2)
+    // stake_lamports is the per-seat stake on the larger side; smaller-side seats stake proportionally more.
+    // expires_at must fall within [now + config.min_lobby_ttl_secs, now + config.max_lobby_ttl_secs].
+    // commitment: Some(commitment_hash(lobby, creator, secret)) makes it a commit-reveal lobby (no oracle, every
+    // joiner commits too; 1v1 only, see MAX_COMMIT_REVEAL_PLAYERS), None uses config.randomness_provider.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_lobby(
+        ctx: Context<CreateLobby>,
+        lobby_id: u64,
+        team1_size: u8,         // seats on team1
+        team2_size: u8,         // seats on team2 (1v1 / 2v2 / 5v5 / 1v10 / 2v20 / 4v40, either orientation)
+        stake_lamports: u64,
+        side: u8,               // 0 or 1
+        game_key: GameKey,      // registered game / game_mode / arena_type IDs
+        expires_at: i64,        // unix time after which the unfilled lobby can be refunded by anyone
+        active_slot: u8,        // free ActiveLobby slot, < config.max_active_lobbies
+        commitment: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let randomness_provider = provider_for_new_lobby(&ctx.accounts.config, &commitment);
+        let params = NewLobby { team1_size, team2_size, stake_lamports, game_key, expires_at, active_slot, randomness_provider };
+        let now = Clock::get()?.unix_timestamp;
+        validate_new_lobby(&ctx.accounts.config, &ctx.accounts.game_entry, &params, now)?;
+        require!(side <= 1, PvpError::InvalidSide);
+
+        // Initialize lobby state
+        let lobby = &mut ctx.accounts.lobby;
+        lobby.set_inner(new_open_lobby(ctx.bumps.lobby, lobby_id, ctx.accounts.creator.key(), &params, now, Clock::get()?.slot));
+
+        // Occupy the active slot (init fails while another lobby holds it)
+        let active = &mut ctx.accounts.active;
+        active.bump    = ctx.bumps.active;
+        active.creator = lobby.creator;
+        active.lobby   = lobby.key();
+
+        // Creator pays and joins immediately
+        let lobby_ai = lobby.to_account_info();
+        internal_join_side(
+            &ctx.accounts.creator,
+            lobby,
+            lobby_ai,
+            ctx.accounts.system_program.to_account_info(),
+            side,
+            commitment,
+        )?;
+
+        // Emit lobby created event
+        emit!(LobbyCreated {
+            lobby: lobby.key(),
+            lobby_id,
+            creator: ctx.accounts.creator.key(),
+            stake_lamports,
+            team1_size,
+            team2_size,
+            created_at: lobby.created_at,
+            game_key,
+            creator_side: side, // Include which team creator joined
+            expires_at,
+            active_slot,
+            randomness_provider,
+            created_slot: lobby.created_slot,
+        });
+
+        Ok(())
+    }
+
+    // Rematch: any player of a Resolved lobby opens a new lobby with the same game key, team shape and stake,
+    // joining on `side`. The other previous players get their seats reserved for REMATCH_RESERVE_SECS
+    // (capped at expires_at); after that anyone can join. The registry and limits are checked again.
+    // commitment: as in create_lobby (Some = commit-reveal rematch, so only of a 1v1 lobby).
+    pub fn rematch(
+        ctx: Context<Rematch>,
+        lobby_id: u64,
+        side: u8,
+        expires_at: i64,
+        active_slot: u8,
+        commitment: Option<[u8; 32]>,
+    ) -> Result<()> {
+        let previous = &ctx.accounts.previous;
+        let creator = ctx.accounts.creator.key();
+        require!(
+            matches!(previous.status, LobbyStatus::Resolved)
+                && (previous.team1.contains(&creator) || previous.team2.contains(&creator)),
+            PvpError::RematchNotAllowed
+        );
+        require!(previous.rematch_lobby == Pubkey::default(), PvpError::AlreadyRematched);
+        let params = NewLobby {
+            team1_size: previous.team1_size,
+            team2_size: previous.team2_size,
+            stake_lamports: previous.stake_lamports,
+            game_key: previous.game_key,
+            expires_at,
+            active_slot,
+            randomness_provider: provider_for_new_lobby(&ctx.accounts.config, &commitment),
+        };
+        let reserved: Vec<Pubkey> = previous.team1.iter().chain(previous.team2.iter())
+            .filter(|k| **k != creator)
+            .copied()
+            .collect();
+        let previous_key = previous.key();
+
+        let now = Clock::get()?.unix_timestamp;
+        validate_new_lobby(&ctx.accounts.config, &ctx.accounts.game_entry, &params, now)?;
+        require!(side <= 1, PvpError::InvalidSide);
+
+        let lobby = &mut ctx.accounts.lobby;
+        lobby.set_inner(new_open_lobby(ctx.bumps.lobby, lobby_id, creator, &params, now, Clock::get()?.slot));
+        // Reservations count towards the account size (space_with_players), so record them before the
+        // creator's join: a commit-reveal seat is larger than the PER_PLAYER the account was created with,
+        // and internal_join_side grows the account (at the creator's cost) only if it knows about them.
+        lobby.reserved = reserved;
+
+        let active = &mut ctx.accounts.active;
+        active.bump    = ctx.bumps.active;
+        active.creator = creator;
+        active.lobby   = lobby.key();
+
+        let lobby_ai = lobby.to_account_info();
+        internal_join_side(
+            &ctx.accounts.creator,
+            lobby,
+            lobby_ai,
+            ctx.accounts.system_program.to_account_info(),
+            side,
+            commitment,
+        )?;
+
+        // Hold the reserved seats only after the creator took theirs
+        lobby.reserved_until = now.saturating_add(REMATCH_RESERVE_SECS).min(expires_at);
+        ctx.accounts.previous.rematch_lobby = lobby.key();
+
+        emit!(LobbyCreated {
+            lobby: lobby.key(),
+            lobby_id,
+            creator,
+            stake_lamports: params.stake_lamports,
+            team1_size: params.team1_size,
+            team2_size: params.team2_size,
+            created_at: now,
+            game_key: params.game_key,
+            creator_side: side,
+            expires_at,
+            active_slot,
+            randomness_provider: params.randomness_provider,
+            created_slot: lobby.created_slot,
+        });
+        emit!(LobbyRematched {
+            lobby: lobby.key(),
+            previous_lobby: previous_key,
+            reserved_count: lobby.reserved.len() as u8,
+            reserved_until: lobby.reserved_until,
+        });
+
+        Ok(())
+    }
+
+    // A player joins a side (0 or 1, or SIDE_ANY) - for non-final joins only.
+    // If this join would fill the lobby, this will error - caller must use join_side_final instead.
+    // commitment: required for commit-reveal lobbies (commitment_hash(lobby, player, secret)), None otherwise.
+    // Commit-reveal lobbies are 1v1, so their only join fills them: it goes through join_side_final.
+    pub fn join_side(ctx: Context<JoinSideSimple>, side: u8, commitment: Option<[u8; 32]>) -> Result<()> {
+        require!(!ctx.accounts.config.is_paused(PAUSE_JOIN), PvpError::JoinPaused);
+        require!(side <= 1 || side == SIDE_ANY, PvpError::InvalidSide);
+
+        // Must be Open to accept more players
+        let lobby = &mut ctx.accounts.lobby;
+        require!(matches!(lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpenForJoin);
+        let side = lobby.pick_side(side);
+
+        // Collect stake and add player
+        let lobby_ai = lobby.to_account_info();
+        internal_join_side(
+            &ctx.accounts.player,
+            lobby,
+            lobby_ai,
+            ctx.accounts.system_program.to_account_info(),
+            side,
+            commitment,
+        )?;
+
+        // Check if lobby is now full
+        let full_now = lobby.is_full();
+
+        // Emit player joined event
+        emit!(PlayerJoined {
+            lobby: lobby.key(),
+            player: ctx.accounts.player.key(),
+            side,
+            team1_count: lobby.team1.len() as u8,
+            team2_count: lobby.team2.len() as u8,
+            is_full: full_now,
+            vrf_request: lobby.vrf_request, // Not set yet for non-final joins
+        });
+
+        // If lobby is full, this is an error - should have used join_side_final
+        require!(!full_now, PvpError::MustUseFinalJoin);
+
+        Ok(())
+    }
+
+    // Final join - when this join will fill the lobby and trigger VRF request.
+    // side: 0 / 1 / SIDE_ANY. IMPORTANT: Caller must provide the accounts of config.randomness_provider.
+    // Orao: vrf_nonce is any value; the seed is derive_vrf_seed(lobby, lobby_id, vrf_nonce) and vrf_request must be
+    // its Orao request PDA. If that PDA is already taken, retry with another nonce.
+    // Switchboard On-Demand: vrf_request is the randomness account the final joiner controls, committed in the previous
+    // slot; vrf_nonce is unused.
+    // Commit-reveal lobbies (1v1): no provider accounts; the opponent's join carries the commitment and opens the
+    // reveal window.
+    pub fn join_side_final(
+        ctx: Context<JoinSideFull>,
+        side: u8,
+        vrf_nonce: u64,
+        commitment: Option<[u8; 32]>,
+    ) -> Result<()> {
+        msg!("🎯 join_side_final CALLED - side: {}, vrf_nonce: {}", side, vrf_nonce);
+        require!(!ctx.accounts.config.is_paused(PAUSE_JOIN), PvpError::JoinPaused);
+        require!(side <= 1 || side == SIDE_ANY, PvpError::InvalidSide);
+
+        // Must be Open to accept more players
+        let lobby = &mut ctx.accounts.lobby;
+        require!(matches!(lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpenForJoin);
+        let side = lobby.pick_side(side);
+
+        // Collect stake and add player
+        let lobby_ai = lobby.to_account_info();
+        internal_join_side(
+            &ctx.accounts.player,
+            lobby,
+            lobby_ai,
+            ctx.accounts.system_program.to_account_info(),
+            side,
+            commitment,
+        )?;
+
+        // Check if lobby is now full
+        let full_now = lobby.is_full();
+
+        // This instruction should only be called when lobby becomes full
+        if full_now {
+            msg!("🎲 Lobby FULL! Requesting VRF...");
+            let orao = OraoAccounts::from_optional(
+                &ctx.accounts.vrf_program,
+                &ctx.accounts.vrf_config,
+                &ctx.accounts.vrf_treasury,
+                ctx.accounts.system_program.to_account_info(),
+            );
+            let lobby_key = lobby.key();
+            // Oracle lobbies use the provider configured now (fail-over), commit-reveal lobbies stay commit-reveal
+            let provider = if lobby.randomness_provider == RandomnessProvider::CommitReveal {
+                RandomnessProvider::CommitReveal
+            } else {
+                ctx.accounts.config.randomness_provider
+            };
+            request_randomness(
+                lobby,
+                lobby_key,
+                provider,
+                ctx.accounts.player.to_account_info(),
+                ctx.accounts.vrf_request.as_ref().map(|r| r.to_account_info()),
+                orao,
+                vrf_nonce,
+            )?;
+            msg!("Lobby full! VRF requested. Waiting for fulfillment, then call resolve_match.");
+        }
+
+        // Emit player joined event AFTER VRF request (so vrf_request is included)
+        emit!(PlayerJoined {
+            lobby: lobby.key(),
+            player: ctx.accounts.player.key(),
+            side,
+            team1_count: lobby.team1.len() as u8,
             team2_count: lobby.team2.len() as u8,
             is_full: full_now,
             vrf_request: lobby.vrf_request,
@@ -540,6 +2450,187 @@ pub mod pvp_program {
         Ok(())
     }
 
+    // Quick play: joins the first candidate lobby (remaining_accounts, in order) that is Open, unexpired,
+    // has exactly this game_key / team shape / stake and a free seat for the player (side picked as SIDE_ANY).
+    // Commit-reveal lobbies are skipped (they need a commitment) and the fallback lobby uses the config provider.
+    // Taking the last seat requests VRF like join_side_final, so candidates that would fill are skipped
+    // unless the provider accounts are passed and vrf_request can serve that candidate (randomness_request_ok).
+    // With no match, creates lobby `lobby_id`
+    // (same rules as create_lobby) with the player as creator on team1.
+    #[allow(clippy::too_many_arguments)]
+    pub fn quick_play<'info>(
+        ctx: Context<'_, '_, '_, 'info, QuickPlay<'info>>,
+        game_key: GameKey,
+        team1_size: u8,
+        team2_size: u8,
+        stake_lamports: u64,
+        vrf_nonce: u64,         // for a filling join (see join_side_final)
+        lobby_id: u64,          // fallback create
+        expires_at: i64,        // fallback create
+        active_slot: u8,        // fallback create
+    ) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let now = Clock::get()?.unix_timestamp;
+        let sys_ai = ctx.accounts.system_program.to_account_info();
+        let provider = ctx.accounts.config.randomness_provider;
+        let orao = OraoAccounts::from_optional(
+            &ctx.accounts.vrf_program,
+            &ctx.accounts.vrf_config,
+            &ctx.accounts.vrf_treasury,
+            sys_ai.clone(),
+        );
+
+        if !ctx.accounts.config.is_paused(PAUSE_JOIN) {
+            for lobby_ai in ctx.remaining_accounts.iter() {
+                if lobby_ai.owner != &crate::ID || !lobby_ai.is_writable {
+                    continue;
+                }
+                // Only this program writes the Lobby discriminator, so a decoded account is a real lobby
+                let Ok(mut lobby) = Lobby::try_deserialize(&mut &lobby_ai.try_borrow_data()?[..]) else {
+                    continue;
+                };
+                if !lobby.matches_quick_play(game_key, team1_size, team2_size, stake_lamports, &player_key, now) {
+                    continue;
+                }
+                let fills = lobby.team1.len() + lobby.team2.len() + 1
+                    == lobby.team1_size as usize + lobby.team2_size as usize;
+                if fills {
+                    let request_ok = ctx.accounts.vrf_request.as_ref().is_some_and(|request| {
+                        randomness_request_ok(provider, &lobby_ai.key(), &lobby, &player_key, vrf_nonce, request, orao.is_some())
+                    });
+                    if !request_ok {
+                        continue;
+                    }
+                }
+
+                let side = lobby.pick_side(SIDE_ANY);
+                internal_join_side(&ctx.accounts.player, &mut lobby, lobby_ai.clone(), sys_ai.clone(), side, None)?;
+                if let (true, Some(request)) = (fills, ctx.accounts.vrf_request.as_ref()) {
+                    request_randomness(
+                        &mut lobby,
+                        lobby_ai.key(),
+                        provider,
+                        ctx.accounts.player.to_account_info(),
+                        Some(request.to_account_info()),
+                        orao.clone(),
+                        vrf_nonce,
+                    )?;
+                }
+                lobby.try_serialize(&mut &mut lobby_ai.try_borrow_mut_data()?[..])?;
+
+                emit!(PlayerJoined {
+                    lobby: lobby_ai.key(),
+                    player: player_key,
+                    side,
+                    team1_count: lobby.team1.len() as u8,
+                    team2_count: lobby.team2.len() as u8,
+                    is_full: fills,
+                    vrf_request: lobby.vrf_request,
+                });
+                return Ok(());
+            }
+        }
+
+        // No match: create a new lobby with the player as creator
+        let randomness_provider = ctx.accounts.config.randomness_provider;
+        let params = NewLobby { team1_size, team2_size, stake_lamports, game_key, expires_at, active_slot, randomness_provider };
+        validate_new_lobby(&ctx.accounts.config, &ctx.accounts.game_entry, &params, now)?;
+
+        let id_bytes = lobby_id.to_le_bytes();
+        let (lobby_key, lobby_bump) =
+            Pubkey::find_program_address(&[SEED_LOBBY, player_key.as_ref(), &id_bytes], &crate::ID);
+        let slot_seed = active_slot_seed(&active_slot);
+        let (active_key, active_bump) =
+            Pubkey::find_program_address(&[SEED_ACTIVE, player_key.as_ref(), slot_seed], &crate::ID);
+        let lobby_ai = ctx.accounts.new_lobby.to_account_info();
+        let active_ai = ctx.accounts.new_active.to_account_info();
+        require_keys_eq!(lobby_ai.key(), lobby_key, PvpError::Unauthorized);
+        require_keys_eq!(active_ai.key(), active_key, PvpError::Unauthorized);
+
+        let payer_ai = ctx.accounts.player.to_account_info();
+        create_pda_account(
+            &payer_ai,
+            &lobby_ai,
+            &sys_ai,
+            Lobby::space_for_seats(1),
+            &[SEED_LOBBY, player_key.as_ref(), &id_bytes, &[lobby_bump]],
+        )?;
+        create_pda_account(
+            &payer_ai,
+            &active_ai,
+            &sys_ai,
+            ActiveLobby::SIZE,
+            &[SEED_ACTIVE, player_key.as_ref(), slot_seed, &[active_bump]],
+        )?;
+        let active = ActiveLobby { bump: active_bump, creator: player_key, lobby: lobby_key };
+        active.try_serialize(&mut &mut active_ai.try_borrow_mut_data()?[..])?;
+
+        let mut lobby = new_open_lobby(lobby_bump, lobby_id, player_key, &params, now, Clock::get()?.slot);
+        internal_join_side(&ctx.accounts.player, &mut lobby, lobby_ai.clone(), sys_ai, 0, None)?;
+        lobby.try_serialize(&mut &mut lobby_ai.try_borrow_mut_data()?[..])?;
+
+        emit!(LobbyCreated {
+            lobby: lobby_key,
+            lobby_id,
+            creator: player_key,
+            stake_lamports,
+            team1_size,
+            team2_size,
+            created_at: now,
+            game_key,
+            creator_side: 0,
+            expires_at,
+            active_slot,
+            randomness_provider,
+            created_slot: lobby.created_slot,
+        });
+
+        Ok(())
+    }
+
+    // A player leaves an Open lobby before it fills: stake and seat rent go back, the account shrinks.
+    // The creator can't leave (their lobby would be left without an owner seat) - refund it instead.
+    pub fn leave_lobby(ctx: Context<LeaveLobby>) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let lobby = &mut ctx.accounts.lobby;
+        require!(matches!(lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpen);
+        require!(!lobby.finalized, PvpError::AlreadyFinalized);
+        require!(player_key != lobby.creator, PvpError::CreatorCannotLeave);
+
+        let (side, refunded) = internal_leave_side(&ctx.accounts.player.to_account_info(), lobby)?;
+
+        emit!(PlayerLeft {
+            lobby: lobby.key(),
+            player: player_key,
+            side,
+            team1_count: lobby.team1.len() as u8,
+            team2_count: lobby.team2.len() as u8,
+            refunded,
+        });
+
+        Ok(())
+    }
+
+    // Creator cancels their lobby while they're still alone in it - no refund lock.
+    // Both PDAs are closed to the creator, which returns the stake together with the rent.
+    pub fn cancel_lobby(ctx: Context<CancelLobby>) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        require!(matches!(lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpen);
+        require!(!lobby.finalized, PvpError::AlreadyFinalized);
+        require!(lobby.team1.len() + lobby.team2.len() == 1, PvpError::LobbyNotEmpty);
+
+        let creator_side = if lobby.team1.is_empty() { 1 } else { 0 };
+        emit!(LobbyRefunded {
+            lobby: lobby.key(),
+            refunded_count: 1,
+            total_refunded: lobby.stake_for_side(creator_side),
+            cranker: lobby.creator,
+            crank_reward: 0,
+        });
+
+        Ok(())
+    }
+
     // Refund is only possible if the lobby is still Open and older than lock.
     // remaining_accounts must include all participants (team1..., team2...),
     // so we can transfer directly from the lobby PDA with signer seeds.
@@ -549,193 +2640,280 @@ pub mod pvp_program {
         // Read-only checks first
         require!(matches!(ctx.accounts.lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpen);
         let now = Clock::get()?.unix_timestamp;
-        require!(now >= ctx.accounts.lobby.created_at + REFUND_LOCK_SECS, PvpError::TooSoonToRefund);
+        require!(
+            now >= ctx.accounts.lobby.created_at + ctx.accounts.config.refund_lock_secs,
+            PvpError::TooSoonToRefund
+        );
         let req = ctx.accounts.requester.key();
-        require!(req == ctx.accounts.lobby.creator || req == ADMIN_PUBKEY, PvpError::Unauthorized);
-        require!(!ctx.accounts.lobby.finalized, PvpError::AlreadyFinalized);
-        
-        // Save all values before mutable borrow
-        let stake_amount = ctx.accounts.lobby.stake_lamports;
-        let lobby_creator = ctx.accounts.lobby.creator;
-        let lobby_id = ctx.accounts.lobby.lobby_id;
-        let lobby_bump = ctx.accounts.lobby.bump;
-        let team1_players: Vec<Pubkey> = ctx.accounts.lobby.team1.clone();
-        let team2_players: Vec<Pubkey> = ctx.accounts.lobby.team2.clone();
-        let total = team1_players.len() + team2_players.len();
-        require!(ctx.remaining_accounts.len() == total, PvpError::BadRemainingAccounts);
-        
-        // Mark finalized and change status - must do this before transfers to avoid reentrancy
-        {
-            let lobby = &mut ctx.accounts.lobby;
-            lobby.finalized = true;
-            lobby.status = LobbyStatus::Refunded;
+        if req != ctx.accounts.lobby.creator {
+            let role = if req == ctx.accounts.config.admin {
+                Role::Admin
+            } else if has_role(&ctx.accounts.config, ctx.accounts.role.as_ref(), &req, Role::RefundOperator) {
+                Role::RefundOperator
+            } else {
+                return err!(PvpError::Unauthorized);
+            };
+            emit!(PrivilegeUsed { authority: req, role, op: PrivilegedOp::Refund });
         }
-
-        let sys_ai  = ctx.accounts.system_program.to_account_info();
-        let from_ai = ctx.accounts.lobby.to_account_info();
+        require!(!ctx.accounts.lobby.finalized, PvpError::AlreadyFinalized);
         
-        // Refund team1
-        for (i, p) in team1_players.iter().enumerate() {
-            let to_ai = &ctx.remaining_accounts[i];
-            require!(to_ai.key() == *p, PvpError::Unauthorized);
-            pay_from_lobby_pda(
-                lobby_creator,
-                lobby_id,
-                lobby_bump,
-                sys_ai.clone(),
-                from_ai.clone(),
-                to_ai.clone(),
-                stake_amount
-            )?;
-        }
-        // Refund team2
-        for (j, p) in team2_players.iter().enumerate() {
-            let idx = team1_players.len() + j;
-            let to_ai = &ctx.remaining_accounts[idx];
-            require!(to_ai.key() == *p, PvpError::Unauthorized);
-            pay_from_lobby_pda(
-                lobby_creator,
-                lobby_id,
-                lobby_bump,
-                sys_ai.clone(),
-                from_ai.clone(),
-                to_ai.clone(),
-                stake_amount
-            )?;
-        }
+        let (refunded_count, total_refunded) = refund_participants(
+            &mut ctx.accounts.lobby,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+            0,
+        )?;
 
-        // Emit refund event
         emit!(LobbyRefunded {
             lobby: ctx.accounts.lobby.key(),
-            refunded_count: total as u8,
-            total_refunded: stake_amount * total as u64,
+            refunded_count,
+            total_refunded,
+            cranker: req,
+            crank_reward: 0,
         });
 
         Ok(())
     }
 
-    // Force refund - allows refund in any status (admin/creator only)
-    // Use this to unstuck broken lobbies (e.g. Pending with wrong randomness account)
-    pub fn force_refund<'info>(ctx: Context<'_, '_, '_, 'info, Refund<'info>>) -> Result<()> {
+    // Force refund - unsticks broken Open / Pending lobbies (admin/refund operator/creator only)
+    // e.g. Pending with wrong randomness account. Never allowed once the VRF request is fulfilled
+    // (the result is known - resolve_match must settle it), and only after config.force_refund_min_stuck_secs.
+    // Pending commit-reveal lobbies always settle through resolve_match (reveals or forfeits).
+    // Pending lobbies must pass their vrf_request. With the council enabled, the admin path needs an approved
+    // ForceRefund proposal. Every call emits ForceRefundAudit with the caller's reason.
+    pub fn force_refund<'info>(ctx: Context<'_, '_, '_, 'info, Refund<'info>>, reason: ForceRefundReason) -> Result<()> {
         require!(ctx.accounts.creator.key() == ctx.accounts.lobby.creator, PvpError::Unauthorized);
-        
-        // Check authorization - must be creator or admin
-        let req = ctx.accounts.requester.key();
-        require!(req == ctx.accounts.lobby.creator || req == ADMIN_PUBKEY, PvpError::Unauthorized);
         require!(!ctx.accounts.lobby.finalized, PvpError::AlreadyFinalized);
-        
-        // Save all values before mutable borrow
-        let stake_amount = ctx.accounts.lobby.stake_lamports;
-        let lobby_creator = ctx.accounts.lobby.creator;
-        let lobby_id = ctx.accounts.lobby.lobby_id;
-        let lobby_bump = ctx.accounts.lobby.bump;
-        let team1_players: Vec<Pubkey> = ctx.accounts.lobby.team1.clone();
-        let team2_players: Vec<Pubkey> = ctx.accounts.lobby.team2.clone();
-        let total = team1_players.len() + team2_players.len();
-        require!(ctx.remaining_accounts.len() == total, PvpError::BadRemainingAccounts);
-        
-        // Mark finalized and change status
-        {
-            let lobby = &mut ctx.accounts.lobby;
-            lobby.finalized = true;
-            lobby.status = LobbyStatus::Refunded;
-        }
 
-        let sys_ai  = ctx.accounts.system_program.to_account_info();
-        let from_ai = ctx.accounts.lobby.to_account_info();
-        
-        // Refund team1
-        for (i, p) in team1_players.iter().enumerate() {
-            let to_ai = &ctx.remaining_accounts[i];
-            require!(to_ai.key() == *p, PvpError::Unauthorized);
-            pay_from_lobby_pda(
-                lobby_creator,
-                lobby_id,
-                lobby_bump,
-                sys_ai.clone(),
-                from_ai.clone(),
-                to_ai.clone(),
-                stake_amount
-            )?;
-        }
-        // Refund team2
-        for (j, p) in team2_players.iter().enumerate() {
-            let idx = team1_players.len() + j;
-            let to_ai = &ctx.remaining_accounts[idx];
-            require!(to_ai.key() == *p, PvpError::Unauthorized);
-            pay_from_lobby_pda(
-                lobby_creator,
-                lobby_id,
-                lobby_bump,
-                sys_ai.clone(),
-                from_ai.clone(),
-                to_ai.clone(),
-                stake_amount
-            )?;
-        }
+        // Stuck since creation (Open) or since the VRF request (Pending)
+        let status = ctx.accounts.lobby.status;
+        let stuck_since = match status {
+            LobbyStatus::Open => ctx.accounts.lobby.created_at,
+            LobbyStatus::Pending => {
+                let vrf_request = ctx.accounts.vrf_request.as_ref().ok_or(PvpError::WrongRandomnessAccount)?;
+                require!(
+                    !randomness_settleable(&ctx.accounts.lobby, vrf_request),
+                    PvpError::RandomnessAlreadyFulfilled
+                );
+                ctx.accounts.lobby.pending_since
+            }
+            _ => return err!(PvpError::AlreadyFinalized),
+        };
+        let stuck_secs = Clock::get()?.unix_timestamp.saturating_sub(stuck_since);
+        require!(
+            stuck_secs >= ctx.accounts.config.force_refund_min_stuck_secs,
+            PvpError::NotStuckLongEnough
+        );
+
+        // Check authorization - must be creator, refund operator or admin
+        let req = ctx.accounts.requester.key();
+        let role = if req == ctx.accounts.lobby.creator {
+            None
+        } else {
+            let role = if has_role(&ctx.accounts.config, ctx.accounts.role.as_ref(), &req, Role::RefundOperator) {
+                Role::RefundOperator
+            } else {
+                let action = AdminAction::ForceRefund { lobby: ctx.accounts.lobby.key() };
+                authorize_admin_action(&ctx.accounts.config, &req, ctx.accounts.proposal.as_mut(), &action)?
+            };
+            emit!(PrivilegeUsed { authority: req, role, op: PrivilegedOp::ForceRefund });
+            Some(role)
+        };
+        emit!(ForceRefundAudit {
+            lobby: ctx.accounts.lobby.key(),
+            authority: req,
+            role,
+            reason,
+            status,
+            stuck_secs,
+        });
+
+        let (refunded_count, total_refunded) = refund_participants(
+            &mut ctx.accounts.lobby,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.remaining_accounts,
+            0,
+        )?;
+
+        emit!(LobbyRefunded {
+            lobby: ctx.accounts.lobby.key(),
+            refunded_count,
+            total_refunded,
+            cranker: req,
+            crank_reward: 0,
+        });
+
+        msg!("Force refund completed for lobby previously in status: {:?}", status);
+        Ok(())
+    }
+
+    // Timeout refund - permissionless way out of Pending when the VRF request is never fulfilled.
+    // Opens once the lobby has been Pending for config.vrf_timeout_secs and the request provably holds no
+    // randomness (Switchboard: the lobby's commit is still current and unrevealed).
+    // Not for commit-reveal lobbies: missed reveals are settled by resolve_match after the reveal deadline.
+    // remaining_accounts must include all participants (team1..., team2...).
+    pub fn timeout_refund<'info>(ctx: Context<'_, '_, '_, 'info, TimeoutRefund<'info>>) -> Result<()> {
+        require!(matches!(ctx.accounts.lobby.status, LobbyStatus::Pending), PvpError::NotPending);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= ctx.accounts.lobby.pending_since.saturating_add(ctx.accounts.config.vrf_timeout_secs),
+            PvpError::VrfTimeoutNotElapsed
+        );
+        // Only on proof that no result exists: a revealed or withdrawn Switchboard commit settles instead
+        require!(
+            !randomness_settleable(&ctx.accounts.lobby, &ctx.accounts.vrf_request),
+            PvpError::RandomnessAlreadyFulfilled
+        );
+
+        // Permissionless: the requester earns the cranker reward
+        cranked_refund(
+            &mut ctx.accounts.lobby,
+            &ctx.accounts.config,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.requester.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
 
-        // Emit refund event
-        emit!(LobbyRefunded {
-            lobby: ctx.accounts.lobby.key(),
-            refunded_count: total as u8,
-            total_refunded: stake_amount * total as u64,
-        });
+        msg!("Timeout refund by {} after {}s pending", ctx.accounts.requester.key(), now - ctx.accounts.lobby.pending_since);
+        Ok(())
+    }
+
+    // Expire refund - permissionless refund of an unfilled lobby once expires_at has passed.
+    // remaining_accounts must include all participants (team1..., team2...).
+    pub fn expire_refund<'info>(ctx: Context<'_, '_, '_, 'info, ExpireRefund<'info>>) -> Result<()> {
+        require!(matches!(ctx.accounts.lobby.status, LobbyStatus::Open), PvpError::LobbyNotOpen);
+        require!(Clock::get()?.unix_timestamp >= ctx.accounts.lobby.expires_at, PvpError::LobbyNotExpired);
+
+        // Permissionless: the requester earns the cranker reward
+        cranked_refund(
+            &mut ctx.accounts.lobby,
+            &ctx.accounts.config,
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.requester.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+
+        Ok(())
+    }
+
+    // Commit-reveal lobbies: once full, each player reveals the secret behind their commitment
+    // (commitment_hash(lobby, player, secret)) before pending_since + REVEAL_WINDOW_SECS.
+    // Never paused, so a pause can't make anyone miss the deadline.
+    pub fn reveal(ctx: Context<Reveal>, secret: [u8; 32]) -> Result<()> {
+        let lobby_key = ctx.accounts.lobby.key();
+        let player_key = ctx.accounts.player.key();
+        let lobby = &mut ctx.accounts.lobby;
+        require!(lobby.randomness_provider == RandomnessProvider::CommitReveal, PvpError::NotCommitReveal);
+        require!(matches!(lobby.status, LobbyStatus::Pending), PvpError::NotPending);
+        require!(Clock::get()?.unix_timestamp <= lobby.reveal_deadline(), PvpError::RevealWindowClosed);
+
+        let entry = lobby.commitments.iter_mut()
+            .find(|c| c.player == player_key)
+            .ok_or(PvpError::NotJoined)?;
+        require!(!entry.revealed, PvpError::AlreadyRevealed);
+        require!(entry.value == commitment_hash(&lobby_key, &player_key, &secret), PvpError::CommitmentMismatch);
+        entry.value    = secret; // the commitment is no longer needed, keep the secret for reveal_randomness
+        entry.revealed = true;
 
-        msg!("Force refund completed for lobby in status: {:?}", ctx.accounts.lobby.status);
+        emit!(SecretRevealed {
+            lobby: lobby_key,
+            player: player_key,
+            revealed_count: lobby.commitments.iter().filter(|c| c.revealed).count() as u8,
+            player_count: lobby.commitments.len() as u8,
+        });
         Ok(())
     }
 
-    // Resolve match using Switchboard OnDemand randomness
+    // Resolve match using the randomness of the lobby's provider
     // This is called after lobby is full (Pending status) to determine winner and pay out
-    // OnDemand uses pull model: we read randomness when needed instead of callback
+    // Orao: once the request is fulfilled. Switchboard On-Demand: once the oracle revealed the lobby's commit
+    // (usually bundled with the reveal ix), or against the final joiner's side once they withdrew it
+    // Commit-reveal: once everyone revealed, or after the reveal deadline with the forfeits (see forfeit_payouts)
     //
     // remaining_accounts must include:
-    // [admin (config.admin), team1..., team2...]
+    // [treasury (config.treasury), team1..., team2...]
     pub fn resolve_match<'info>(ctx: Context<'_, '_, '_, 'info, ResolveMatch<'info>>) -> Result<()> {
         require!(ctx.accounts.creator.key() == ctx.accounts.lobby.creator, PvpError::Unauthorized);
 
         // Read-only checks first
+        require!(!ctx.accounts.config.is_paused(PAUSE_RESOLVE), PvpError::ResolvePaused);
         require!(matches!(ctx.accounts.lobby.status, LobbyStatus::Pending), PvpError::NotPending);
         require!(!ctx.accounts.lobby.finalized, PvpError::AlreadyFinalized);
         
-        // READ RANDOMNESS FROM ORAO VRF (PROOF OF FAIRNESS!)
-        // Manual parsing to avoid Anchor version conflicts
-        let account_data = ctx.accounts.vrf_request.try_borrow_data()
-            .map_err(|_| PvpError::InvalidRandomnessData)?;
-        
-        // Orao VRF RandomnessV2 structure:
-        // [0..8]: Anchor discriminator
-        // [8]: Enum tag (0 = Pending, 1 = Fulfilled)
-        // If Fulfilled (tag=1):
-        //   [9..41]: client pubkey (32 bytes)
-        //   [41..73]: seed (32 bytes)
-        //   [73..137]: randomness (64 bytes)
-        
-        if account_data.len() < 137 {
-            return Err(PvpError::InvalidRandomnessData.into());
-        }
-        
-        // Check if fulfilled (enum tag == 1)
-        let is_fulfilled = account_data[8] == 1;
-        if !is_fulfilled {
-            return Err(PvpError::RandomnessNotFulfilled.into());
-        }
-        
-        // Extract randomness bytes [73..137]
-        let mut randomness_bytes = [0u8; 64];
-        randomness_bytes.copy_from_slice(&account_data[73..137]);
-        
-        // Convert first 8 bytes to u64 for transparency logging
-        let mut randomness_u64_bytes = [0u8; 8];
-        randomness_u64_bytes.copy_from_slice(&randomness_bytes[0..8]);
-        let randomness_value = u64::from_le_bytes(randomness_u64_bytes);
-        
-        msg!("Orao VRF randomness: {}", randomness_value);
-        
-        // Determine winner based on Orao VRF randomness (provably fair!)
-        let winner_side = (randomness_value % 2) as u8;
-        
-        msg!("Winner determined by Orao VRF: Side {}", winner_side);
+        // READ RANDOMNESS FROM THE LOBBY'S PROVIDER (PROOF OF FAIRNESS!)
+        // Oracles: the account must be the provider's and carry this lobby's seed / commit
+        // Commit-reveal: the combined reveals, or the forfeits once the reveal deadline passed
+        let provider = ctx.accounts.lobby.randomness_provider;
+        let lobby_key = ctx.accounts.lobby.key();
+        let settlement = if provider == RandomnessProvider::CommitReveal {
+            reveal_outcome(&lobby_key, &ctx.accounts.lobby, Clock::get()?.unix_timestamp)?
+        } else {
+            let vrf_request = ctx.accounts.vrf_request.as_ref().ok_or(PvpError::WrongRandomnessAccount)?;
+            oracle_outcome(&ctx.accounts.lobby, vrf_request)?
+        };
+
+        let (winner_side, randomness_value) = match settlement {
+            Settlement::Draw(randomness_bytes) => {
+                // Convert first 8 bytes to u64 for transparency logging
+                let mut randomness_u64_bytes = [0u8; 8];
+                randomness_u64_bytes.copy_from_slice(&randomness_bytes[0..8]);
+                let randomness_value = u64::from_le_bytes(randomness_u64_bytes);
+
+                msg!("{:?} randomness: {}", provider, randomness_value);
+
+                // Determine winner based on provider randomness (provably fair!)
+                // First draw of the rng stream over all 64 bytes (randomness_value above is only logged)
+                let mut rng = RngStream::new(&randomness_bytes);
+                let winner_side = rng.below(2).ok_or(PvpError::InvalidRandomnessData)? as u8;
+
+                msg!("Winner determined by {:?} randomness: Side {}", provider, winner_side);
+                (winner_side, randomness_value)
+            }
+            Settlement::Withdrawn(loser_side) => {
+                msg!("Randomness withdrawn by the final joiner: Side {} loses", loser_side);
+                emit!(RandomnessWithdrawn { lobby: lobby_key, vrf_request: ctx.accounts.lobby.vrf_request, loser_side });
+                (1 - loser_side, 0)
+            }
+            Settlement::Forfeit => {
+                // Missed reveals: forfeited stakes go to the revealer, who wins (no platform fee).
+                // Same remaining_accounts as any settlement: [treasury, team1..., team2...]
+                let (treasury_ai, participants) = ctx.remaining_accounts.split_first()
+                    .ok_or(PvpError::BadRemainingAccounts)?;
+                require!(treasury_ai.key() == ctx.accounts.config.treasury, PvpError::Unauthorized);
+
+                let missing = ctx.accounts.lobby.missing_reveals();
+                let total_pot = ctx.accounts.lobby.pot();
+                let payouts = settle_forfeits(
+                    &mut ctx.accounts.lobby,
+                    ctx.accounts.system_program.to_account_info(),
+                    participants,
+                    Clock::get()?.unix_timestamp,
+                )?;
+                emit!(RevealsMissed { lobby: lobby_key, players: missing });
+                if ctx.accounts.lobby.status == LobbyStatus::Resolved {
+                    emit!(LobbyResolved {
+                        lobby: lobby_key,
+                        winner_side: ctx.accounts.lobby.winner_side,
+                        randomness_value: 0, // no randomness: decided by the forfeit
+                        randomness_provider: provider,
+                        total_pot,
+                        platform_fee: 0,
+                        payout_per_winner: payouts.iter().map(|(_, amount)| *amount).max().unwrap_or(0),
+                        cranker: ctx.accounts.cranker.key(),
+                        crank_reward: 0,
+                    });
+                } else {
+                    emit!(LobbyRefunded {
+                        lobby: lobby_key,
+                        refunded_count: payouts.len() as u8,
+                        total_refunded: payouts.iter().map(|(_, amount)| *amount).sum(),
+                        cranker: ctx.accounts.cranker.key(),
+                        crank_reward: 0,
+                    });
+                }
+                return Ok(());
+            }
+        };
         
         // Save all lobby values before mutable borrow
         let lobby_creator = ctx.accounts.lobby.creator;
@@ -743,20 +2921,23 @@ pub mod pvp_program {
         let lobby_bump = ctx.accounts.lobby.bump;
         let team1_players: Vec<Pubkey> = ctx.accounts.lobby.team1.clone();
         let team2_players: Vec<Pubkey> = ctx.accounts.lobby.team2.clone();
-        let stake_lamports = ctx.accounts.lobby.stake_lamports;
         let winners = if winner_side == 0 { &team1_players } else { &team2_players };
         let winners_count = winners.len() as u64;
         require!(winners_count > 0, PvpError::NotEnoughPlayers);
 
-        // Total pot: stake * total players
-        let total_players = (team1_players.len() + team2_players.len()) as u64;
-        let pot = stake_lamports.saturating_mul(total_players);
+        // Total pot: every seat's side stake. Both sides contribute equally, so winners split
+        // the whole pot evenly (the 1 in a 1v10 takes ten larger-side stakes on top of their own).
+        let pot = ctx.accounts.lobby.pot();
 
-        // 1% fee, rounding remainder added to fee
-        let fee = pot.saturating_mul(PLATFORM_FEE_BPS) / 10_000;
+        // Platform fee (config.fee_bps), rounding remainder added to fee
+        let fee = pot.saturating_mul(ctx.accounts.config.fee_bps as u64) / 10_000;
         let distributable = pot.saturating_sub(fee);
         let payout_each = distributable / winners_count;
-        let fee_final = fee + (distributable - payout_each * winners_count);
+        let fee_total = fee + (distributable - payout_each * winners_count);
+
+        // Cranker reward comes out of the fee, never out of the winners' payout
+        let crank_reward = ctx.accounts.config.crank_reward_for(pot).min(fee_total);
+        let fee_final = fee_total - crank_reward;
 
         // remaining_accounts layout: [treasury (fees receiver), team1..., team2...]
         let needed = 1 + team1_players.len() + team2_players.len();
@@ -764,7 +2945,7 @@ pub mod pvp_program {
 
         // Validate first account is treasury (receives platform fees)
         let treasury_ai = &ctx.remaining_accounts[0];
-        require!(treasury_ai.key() == TREASURY_PUBKEY, PvpError::Unauthorized);
+        require!(treasury_ai.key() == ctx.accounts.config.treasury, PvpError::Unauthorized);
 
         // Validate ordering & keys for team lists
         for (i, p) in team1_players.iter().enumerate() {
@@ -778,9 +2959,10 @@ pub mod pvp_program {
         // Mark finalized, save winner, and change status - must do this before transfers
         {
             let lobby = &mut ctx.accounts.lobby;
-            lobby.winner_side = winner_side; // Save Switchboard-determined winner
+            lobby.winner_side = winner_side; // Save randomness-determined winner
             lobby.finalized = true;
             lobby.status = LobbyStatus::Resolved;
+            lobby.settled_at = Clock::get()?.unix_timestamp;
         }
 
         let sys_ai  = ctx.accounts.system_program.to_account_info();
@@ -797,6 +2979,17 @@ pub mod pvp_program {
             fee_final
         )?;
 
+        // Pay cranker
+        pay_from_lobby_pda(
+            lobby_creator,
+            lobby_id,
+            lobby_bump,
+            sys_ai.clone(),
+            from_ai.clone(),
+            ctx.accounts.cranker.to_account_info(),
+            crank_reward
+        )?;
+
         // Pay winners
         if winner_side == 0 {
             for i in 0..team1_players.len() {
@@ -831,10 +3024,66 @@ pub mod pvp_program {
         emit!(LobbyResolved {
             lobby: ctx.accounts.lobby.key(),
             winner_side,
-            randomness_value, // Switchboard randomness for transparency!
+            randomness_value, // provider randomness for transparency!
+            randomness_provider: provider,
             total_pot: pot,
             platform_fee: fee_final,
             payout_per_winner: payout_each,
+            cranker: ctx.accounts.cranker.key(),
+            crank_reward,
+        });
+
+        Ok(())
+    }
+
+    // Closes a settled lobby and returns its rent: every joiner gets back the seat rent paid on join,
+    // the creator receives the rest (header + own seat + any dust). Emits LobbyClosed so indexers can drop it.
+    // A Resolved lobby is what rematch reads, so until REMATCH_WINDOW_SECS after resolution only its creator can close it.
+    // remaining_accounts must include all participants (team1..., team2...), creator included.
+    pub fn close_lobby<'info>(ctx: Context<'_, '_, '_, 'info, CloseLobby<'info>>) -> Result<()> {
+        let lobby = &ctx.accounts.lobby;
+        require!(
+            matches!(lobby.status, LobbyStatus::Resolved | LobbyStatus::Refunded) && lobby.finalized,
+            PvpError::LobbyNotSettled
+        );
+        if lobby.status == LobbyStatus::Resolved && ctx.accounts.closer.key() != lobby.creator {
+            require!(
+                Clock::get()?.unix_timestamp >= lobby.settled_at.saturating_add(REMATCH_WINDOW_SECS),
+                PvpError::RematchWindowOpen
+            );
+        }
+        let total = lobby.team1.len() + lobby.team2.len();
+        require!(ctx.remaining_accounts.len() == total, PvpError::BadRemainingAccounts);
+
+        let refunds = seat_rent_refunds(lobby, &Rent::get()?);
+
+        let sys_ai  = ctx.accounts.system_program.to_account_info();
+        let from_ai = lobby.to_account_info();
+        let mut seat_rent_total = 0u64;
+        let seats = lobby.team1.iter().chain(lobby.team2.iter()).zip(refunds);
+        for ((p, seat_rent), to_ai) in seats.zip(ctx.remaining_accounts.iter()) {
+            require!(to_ai.key() == *p, PvpError::RemainingAccountsMismatch);
+            if seat_rent == 0 {
+                continue;
+            }
+            pay_from_lobby_pda(
+                lobby.creator,
+                lobby.lobby_id,
+                lobby.bump,
+                sys_ai.clone(),
+                from_ai.clone(),
+                to_ai.clone(),
+                seat_rent
+            )?;
+            seat_rent_total += seat_rent;
+        }
+
+        emit!(LobbyClosed {
+            lobby: lobby.key(),
+            closed_by: ctx.accounts.closer.key(),
+            creator_rent: from_ai.lamports(),
+            seat_rent_total,
+            created_slot: lobby.created_slot,
         });
 
         Ok(())
@@ -844,6 +3093,110 @@ pub mod pvp_program {
 // ------------------------------ State ------------------------------
 
 
+// Singleton program configuration (seeds = ["config"]).
+#[account]
+pub struct GlobalConfig {
+    pub bump: u8,
+    pub admin: Pubkey,           // can authorize refunds and update config
+    pub treasury: Pubkey,        // receives platform fees
+    pub fee_bps: u16,            // platform fee in basis points (<= MAX_PLATFORM_FEE_BPS)
+    pub min_stake_lamports: u64, // minimum stake per player
+    pub refund_lock_secs: i64,   // lobby age required before refund
+    pub vrf_timeout_secs: i64,   // Pending time without fulfilled randomness before timeout_refund
+    pub force_refund_min_stuck_secs: i64, // Open / Pending time before force_refund is allowed
+    pub crank_reward_bps: u16,   // cranker reward as a share of the pot (0 = disabled)
+    pub max_crank_reward_lamports: u64, // cap on a single cranker reward
+    pub min_lobby_ttl_secs: i64, // shortest allowed expires_at - now on create_lobby
+    pub max_lobby_ttl_secs: i64, // longest allowed expires_at - now on create_lobby
+    pub max_active_lobbies: u8,  // concurrent lobbies per creator (ActiveLobby slots)
+    pub randomness_provider: RandomnessProvider, // provider used when a lobby fills
+    pub pause_flags: u8,         // PAUSE_* bits currently set
+    pub pending_admin: Pubkey,   // nominee of propose_admin (default = none)
+    pub council_threshold: u8,   // M of the M-of-N council (0 = disabled)
+    pub council_since: i64,      // when the council was last enabled; older role grants stop working (see has_role)
+    pub council: Vec<Pubkey>,    // N council signers (max MAX_COUNCIL_SIGNERS)
+}
+impl GlobalConfig {
+    pub const SIZE: usize = 8 + 1 + 32 + 32 + 2 + 8 + 8 + 8 + 8 + 2 + 8 + 8 + 8 + 1 + 1 + 1 + 32 + 1 + 8 + (4 + 32 * MAX_COUNCIL_SIGNERS);
+
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.pause_flags & flag != 0
+    }
+
+    pub fn council_enabled(&self) -> bool {
+        self.council_threshold > 0
+    }
+
+    pub fn is_council_member(&self, key: &Pubkey) -> bool {
+        self.council.contains(key)
+    }
+
+    // Cranker reward for settling a pot of `pot` lamports: crank_reward_bps of it, capped.
+    pub fn crank_reward_for(&self, pot: u64) -> u64 {
+        let reward = (pot as u128 * self.crank_reward_bps as u128 / 10_000) as u64;
+        reward.min(self.max_crank_reward_lamports)
+    }
+}
+
+// Council proposal (seeds = ["proposal", proposal_id]). Gathers approvals over several transactions
+// and is consumed by the instruction whose AdminAction it carries.
+#[account]
+pub struct AdminProposal {
+    pub bump: u8,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+    pub approvals: Vec<Pubkey>, // council signers that approved
+    pub created_at: i64,
+    pub executed: bool,
+}
+impl AdminProposal {
+    pub const SIZE: usize = 8 + 1 + 8 + 32 + AdminAction::MAX_SIZE + (4 + 32 * MAX_COUNCIL_SIGNERS) + 8 + 1;
+}
+
+// Game registry entry (seeds = ["game", game_key]).
+// create_lobby only accepts combinations that have an enabled entry.
+#[account]
+pub struct GameEntry {
+    pub bump: u8,
+    pub game_key: GameKey,
+    pub game: String,            // max MAX_GAME_LEN
+    pub game_mode: String,       // max MAX_GAME_MODE_LEN
+    pub arena_type: String,      // max MAX_ARENA_TYPE_LEN
+    pub team_shapes: Vec<TeamShape>, // allowed shapes (max MAX_GAME_SHAPES)
+    pub min_stake_lamports: u64,
+    pub max_stake_lamports: u64,
+    pub enabled: bool,
+}
+impl GameEntry {
+    pub const SIZE: usize = 8 + 1 + 3 + (4 + MAX_GAME_LEN) + (4 + MAX_GAME_MODE_LEN) + (4 + MAX_ARENA_TYPE_LEN)
+        + (4 + 2 * MAX_GAME_SHAPES) + 8 + 8 + 1;
+}
+
+// Role grant (seeds = ["role", role, grantee]). Revocation keeps the account with active = false.
+#[account]
+pub struct RoleGrant {
+    pub bump: u8,
+    pub role: Role,
+    pub grantee: Pubkey,
+    pub active: bool,
+    pub granted_by: Pubkey,
+    pub granted_at: i64,
+    pub revoked_at: i64, // 0 while active
+}
+impl RoleGrant {
+    pub const SIZE: usize = 8 + 1 + 1 + 32 + 1 + 32 + 8 + 8;
+}
+
+// Program-owned fee vault (seeds = ["treasury"]); lamports above rent are withdrawable.
+#[account]
+pub struct TreasuryVault {
+    pub bump: u8,
+}
+impl TreasuryVault {
+    pub const SIZE: usize = 8 + 1;
+}
+
 #[account]
 pub struct ActiveLobby {
     pub bump: u8,
@@ -854,52 +3207,634 @@ impl ActiveLobby {
     pub const SIZE: usize = 8 + 1 + 32 + 32;
 }
 
-#[account]
+// Commit-reveal seat: commitment until revealed, then the revealed secret
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SeatCommitment {
+    pub player: Pubkey,
+    pub value: [u8; 32], // commitment_hash(lobby, player, secret), replaced by the secret on reveal
+    pub revealed: bool,
+}
+impl SeatCommitment {
+    pub const SIZE: usize = 32 + 32 + 1;
+}
+
+// Fixed-size fields come first so indexers can memcmp at stable offsets
+// (e.g. Lobby::GAME_KEY_OFFSET to filter by game); the team vectors stay at the end.
+#[account(discriminator = &LOBBY_DISCRIMINATOR)]
 pub struct Lobby {
     pub bump: u8,
     pub lobby_id: u64,
     pub creator: Pubkey,
     pub status: LobbyStatus,
-    pub team_size: u8,          // must be 1, 2, or 5
-    pub stake_lamports: u64,
+    pub team1_size: u8,         // seats on team1
+    pub stake_lamports: u64,    // per seat on the larger side (see stake_for_side)
     pub created_at: i64,
     pub finalized: bool,        // prevents double settlement
-    pub vrf_seed: [u8; 32],     // Orao VRF seed for randomness request
-    pub vrf_request: Pubkey,    // Orao VRF request account PDA (set when full)
+    pub vrf_seed: [u8; 32],     // Orao VRF seed / Switchboard commit slot hash (set when full)
+    pub vrf_request: Pubkey,    // provider randomness account (set when full; default for commit-reveal)
     pub winner_side: u8,        // 0 or 1, set when resolved
+    pub game_key: GameKey,      // game / game_mode / arena_type registry IDs
+    pub team2_size: u8,         // seats on team2 (after game_key to keep earlier offsets stable)
+    pub pending_since: i64,     // when the lobby filled and VRF was requested (0 while Open)
+    pub expires_at: i64,        // creator-chosen deadline for filling the lobby
+    pub active_slot: u8,        // creator's ActiveLobby slot (see active_slot_seed)
+    pub reserved_until: i64,    // rematch: only `reserved` players may join before this
+    pub randomness_provider: RandomnessProvider, // provider of vrf_request (final once Pending)
+    pub settled_at: i64,        // when resolve_match resolved it (0 otherwise); starts the rematch window
+    pub seat_rent_prepaid: bool, // creator paid every seat up front (migrated legacy lobby): joiners pay no seat rent
+    pub created_slot: u64,      // slot of create_lobby; tells apart lobbies re-created at a closed lobby's address
+    pub rematch_lobby: Pubkey,  // lobby created from this one by rematch (default until then; one rematch per lobby)
+    pub randomness_side: u8,    // Switchboard: side of the final joiner, who supplied vrf_request (set when full)
+    pub team1: Vec<Pubkey>,
+    pub team2: Vec<Pubkey>,
+    pub reserved: Vec<Pubkey>,  // rematch seat reservations (previous participants)
+    pub commitments: Vec<SeatCommitment>, // commit-reveal lobbies: one per seat, in join order
+}
+impl Lobby {
+    // Layout size calculation:
+    // discr(8)+bump(1)+lobby_id(8)+creator(32)+status(1)+team1_size(1)+stake(8)+created_at(8)+finalized(1)
+    // +vrf_seed(32)+vrf_request(32)+winner(1)+game_key(3)+team2_size(1)+pending_since(8)+expires_at(8)
+    // +active_slot(1)+reserved_until(8)+randomness_provider(1)+settled_at(8)+seat_rent_prepaid(1)+created_slot(8)
+    // +rematch_lobby(32)+randomness_side(1)+vec headers(4+4+4+4)
+    pub const FIXED: usize = 8 + 1 + 8 + 32 + 1 + 1 + 8 + 8 + 1 + 32 + 32 + 1 + 3 + 1 + 8 + 8 + 1 + 8 + 1 + 8 + 1 + 8 + 32
+        + 1 + 4 + 4 + 4 + 4;
+    pub const PER_PLAYER: usize = 32;
+
+    // Account size for a lobby holding `seats` players (the account grows one seat per join).
+    // Rematch reservations take PER_PLAYER each as well, see space_with_players.
+    pub fn space_for_seats(seats: usize) -> usize {
+        Self::FIXED + Self::PER_PLAYER * seats
+    }
+
+    // Account size of this lobby once it holds `players` players
+    pub fn space_with_players(&self, players: usize) -> usize {
+        Self::FIXED + self.seat_size() * players + Self::PER_PLAYER * self.reserved.len()
+    }
+
+    // Bytes a join adds: the player key, plus their commitment in commit-reveal lobbies
+    pub fn seat_size(&self) -> usize {
+        if self.randomness_provider == RandomnessProvider::CommitReveal {
+            Self::PER_PLAYER + SeatCommitment::SIZE
+        } else {
+            Self::PER_PLAYER
+        }
+    }
+
+    // Last moment reveal accepts secrets (commit-reveal lobbies, once Pending)
+    pub fn reveal_deadline(&self) -> i64 {
+        self.pending_since.saturating_add(REVEAL_WINDOW_SECS)
+    }
+
+    pub fn has_revealed(&self, player: &Pubkey) -> bool {
+        self.commitments.iter().any(|c| c.player == *player && c.revealed)
+    }
+
+    // Participants (team1..., team2...) that haven't revealed yet
+    pub fn missing_reveals(&self) -> Vec<Pubkey> {
+        self.team1.iter().chain(self.team2.iter())
+            .filter(|p| !self.has_revealed(p))
+            .copied()
+            .collect()
+    }
+
+    pub fn side_capacity(&self, side: u8) -> u8 {
+        if side == 0 { self.team1_size } else { self.team2_size }
+    }
+
+    // Resolves SIDE_ANY to the emptier side by fill ratio (so a 1v10 fills both sides evenly);
+    // ties go to team1. Explicit sides pass through unchanged.
+    pub fn pick_side(&self, side: u8) -> u8 {
+        if side != SIDE_ANY {
+            return side;
+        }
+        // team1.len / team1_size <= team2.len / team2_size, cross-multiplied
+        let fill1 = self.team1.len() * self.team2_size as usize;
+        let fill2 = self.team2.len() * self.team1_size as usize;
+        if fill1 <= fill2 { 0 } else { 1 }
+    }
+
+    // Per-seat stake on `side`. Both sides put the same total into the pot, so a seat on the
+    // smaller side stakes stake_lamports * (larger / smaller), e.g. 10x for the 1 in a 1v10.
+    pub fn stake_for_side(&self, side: u8) -> u64 {
+        let own = self.side_capacity(side) as u64;
+        let other = self.side_capacity(1 - side) as u64;
+        if own > 0 && own < other {
+            self.stake_lamports.saturating_mul(other / own)
+        } else {
+            self.stake_lamports
+        }
+    }
+
+    // Sum of all stakes currently in the lobby
+    pub fn pot(&self) -> u64 {
+        self.stake_for_side(0).saturating_mul(self.team1.len() as u64)
+            .saturating_add(self.stake_for_side(1).saturating_mul(self.team2.len() as u64))
+    }
+
+    // Per-seat refund of `side` when `withhold` (cranker reward) is taken from every seat pro rata to its
+    // stake, rounded down, so the total withheld (pot - refunds) never exceeds `withhold`
+    pub fn refund_for_side(&self, side: u8, withhold: u64) -> u64 {
+        let stake = self.stake_for_side(side);
+        let pot = self.pot();
+        if pot == 0 {
+            return stake;
+        }
+        stake - (stake as u128 * withhold.min(pot) as u128 / pot as u128) as u64
+    }
+
+    // Drops `player`'s rematch reservation (if any) so the account stops paying for it. The reservation's
+    // rent, prepaid by the creator, stays with the seat: the player pays only the rest and gets a full seat's
+    // rent back on leave / close like everyone else. Returns whether there was one.
+    pub fn claim_reservation(&mut self, player: &Pubkey) -> bool {
+        let before = self.reserved.len();
+        self.reserved.retain(|k| k != player);
+        self.reserved.len() != before
+    }
+
+    // Whether `player` could take a seat right now (join rules of internal_join_side)
+    pub fn can_seat(&self, player: &Pubkey, now: i64) -> bool {
+        matches!(self.status, LobbyStatus::Open)
+            && !self.finalized
+            && !self.is_full()
+            && now < self.expires_at
+            && (now >= self.reserved_until || self.reserved.contains(player))
+            && !self.team1.contains(player)
+            && !self.team2.contains(player)
+    }
+
+    // Whether quick_play may seat `player` here: same game, shape and stake, an open seat, and an oracle
+    // provider (quick_play carries no commitment, so commit-reveal lobbies are skipped)
+    pub fn matches_quick_play(
+        &self,
+        game_key: GameKey,
+        team1_size: u8,
+        team2_size: u8,
+        stake_lamports: u64,
+        player: &Pubkey,
+        now: i64,
+    ) -> bool {
+        self.game_key == game_key
+            && self.team1_size == team1_size
+            && self.team2_size == team2_size
+            && self.stake_lamports == stake_lamports
+            && self.randomness_provider != RandomnessProvider::CommitReveal
+            && self.can_seat(player, now)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.team1.len() as u8 == self.team1_size && self.team2.len() as u8 == self.team2_size
+    }
+
+    // memcmp offsets (from the start of account data, discriminator included)
+    pub const CREATOR_OFFSET: usize = 8 + 1 + 8;
+    pub const STATUS_OFFSET: usize = Self::CREATOR_OFFSET + 32;
+    pub const GAME_KEY_OFFSET: usize = 8 + 1 + 8 + 32 + 1 + 1 + 8 + 8 + 1 + 32 + 32 + 1;
+}
+
+// Lobby layout before compact game configuration (four Strings after the team vectors).
+// Kept so existing accounts still decode; migrate_lobby converts them to Lobby.
+#[account(discriminator = &LEGACY_LOBBY_DISCRIMINATOR)]
+pub struct LegacyLobby {
+    pub bump: u8,
+    pub lobby_id: u64,
+    pub creator: Pubkey,
+    pub status: LobbyStatus,
+    pub team_size: u8,
+    pub stake_lamports: u64,
+    pub created_at: i64,
+    pub finalized: bool,
+    pub vrf_seed: [u8; 32],
+    pub vrf_request: Pubkey,
+    pub winner_side: u8,
     pub team1: Vec<Pubkey>,
     pub team2: Vec<Pubkey>,
-    // NEW: Game configuration fields
     pub game: String,           // "PickHigher", "Plinko", etc. (max 32 chars)
     pub game_mode: String,      // "1x3", "3x9", "5x16", etc. (max 16 chars)
     pub arena_type: String,     // "SingleBattle", "DeathMatch" (max 32 chars)
     pub team_size_str: String,  // "1v1", "2v2", "5v5", "1v10", etc. (max 16 chars)
 }
-impl Lobby {
-    // Layout size calculation:
-    // Old: discr(8)+bump(1)+lobby_id(8)+creator(32)+status(1)+team_size(1)+stake(8)+created_at(8)+finalized(1)+vrf_seed(32)+vrf_request(32)+winner(1)+vec headers(4+4)
-    // New: + game(4+32) + game_mode(4+16) + arena_type(4+32) + team_size_str(4+16)
-    pub const FIXED: usize = 8 + 1 + 8 + 32 + 1 + 1 + 8 + 8 + 1 + 32 + 32 + 1 + 4 + 4 + (4+32) + (4+16) + (4+32) + (4+16);
-    pub const PER_PLAYER: usize = 32;
-    pub const SIZE: usize = Self::FIXED + (Self::PER_PLAYER * MAX_TEAM_SIZE_ALLOC * 2);
+impl LegacyLobby {
+    pub const FIXED: usize = 8 + 1 + 8 + 32 + 1 + 1 + 8 + 8 + 1 + 32 + 32 + 1 + 4 + 4
+        + (4 + MAX_GAME_LEN) + (4 + MAX_GAME_MODE_LEN) + (4 + MAX_ARENA_TYPE_LEN) + (4 + MAX_TEAM_SIZE_STR_LEN);
 }
 
 // ------------------------------ Internals ------------------------------
 
-// struct JoinParams<'info> {
-//     payer: &'info Signer<'info>,
-//     lobby: &'info mut Account<'info, Lobby>,
-//     system_program: &'info Program<'info, System>,
-// }
+// struct JoinParams<'info> {
+//     payer: &'info Signer<'info>,
+//     lobby: &'info mut Account<'info, Lobby>,
+//     system_program: &'info Program<'info, System>,
+// }
+
+// Parameters shared by create_lobby and rematch
+struct NewLobby {
+    team1_size: u8,
+    team2_size: u8,
+    stake_lamports: u64,
+    game_key: GameKey,
+    expires_at: i64,
+    active_slot: u8,
+    randomness_provider: RandomnessProvider,
+}
+
+// Checks a new lobby against the pause switch, team shapes, stake limits, expiry bounds,
+// active slot limit and the game registry.
+fn validate_new_lobby(config: &GlobalConfig, game_entry_ai: &AccountInfo, params: &NewLobby, now: i64) -> Result<()> {
+    require!(!config.is_paused(PAUSE_CREATE), PvpError::CreatePaused);
+    let shape = TeamShape { team1_size: params.team1_size, team2_size: params.team2_size };
+    require!(shape.is_allowed(), PvpError::InvalidTeamSize);
+    require!(params.stake_lamports >= config.min_stake_lamports, PvpError::StakeTooSmall);
+    require!(params.active_slot < config.max_active_lobbies, PvpError::ActiveSlotOutOfRange);
+    if params.randomness_provider == RandomnessProvider::CommitReveal {
+        require!(
+            params.team1_size as usize + params.team2_size as usize <= MAX_COMMIT_REVEAL_PLAYERS,
+            PvpError::CommitRevealLobbyTooLarge
+        );
+    }
+    require!(
+        params.expires_at >= now.saturating_add(config.min_lobby_ttl_secs)
+            && params.expires_at <= now.saturating_add(config.max_lobby_ttl_secs),
+        PvpError::InvalidExpiry
+    );
+
+    // Only registered game / mode / arena combinations (see set_game)
+    let entry = load_game_entry(game_entry_ai, params.game_key)?;
+    require!(entry.enabled, PvpError::GameDisabled);
+    require!(entry.team_shapes.contains(&shape), PvpError::TeamSizeNotAllowed);
+    require!(
+        (entry.min_stake_lamports..=entry.max_stake_lamports).contains(&params.stake_lamports),
+        PvpError::StakeOutOfRange
+    );
+    // Each side's total (stake_lamports * larger side) must fit twice into the pot
+    require!(
+        params.stake_lamports.checked_mul(2 * params.team1_size.max(params.team2_size) as u64).is_some(),
+        PvpError::StakeOutOfRange
+    );
+    Ok(())
+}
+
+// Randomness mode of a new lobby: a creator commitment makes it commit-reveal, otherwise the config provider
+fn provider_for_new_lobby(config: &GlobalConfig, commitment: &Option<[u8; 32]>) -> RandomnessProvider {
+    if commitment.is_some() { RandomnessProvider::CommitReveal } else { config.randomness_provider }
+}
+
+// Initial Open state of a new lobby (no players yet)
+fn new_open_lobby(bump: u8, lobby_id: u64, creator: Pubkey, params: &NewLobby, now: i64, slot: u64) -> Lobby {
+    Lobby {
+        bump,
+        lobby_id,
+        creator,
+        status: LobbyStatus::Open,
+        team1_size: params.team1_size,
+        stake_lamports: params.stake_lamports,
+        created_at: now,
+        finalized: false,
+        vrf_seed: [0u8; 32],             // will be set in join_side_final
+        vrf_request: Pubkey::default(),  // will be set in join_side_final
+        winner_side: 0,                  // not set yet
+        game_key: params.game_key,
+        team2_size: params.team2_size,
+        pending_since: 0,                // set when the lobby fills
+        expires_at: params.expires_at,
+        active_slot: params.active_slot,
+        reserved_until: 0,               // rematch only
+        randomness_provider: params.randomness_provider, // re-read from config when the lobby fills
+        settled_at: 0,                   // set by resolve_match
+        seat_rent_prepaid: false,        // every joiner pays their own seat
+        created_slot: slot,
+        rematch_lobby: Pubkey::default(),
+        randomness_side: 0,              // set in request_randomness (Switchboard)
+        team1: Vec::with_capacity(params.team1_size as usize),
+        team2: Vec::with_capacity(params.team2_size as usize),
+        reserved: Vec::new(),
+        commitments: Vec::new(),
+    }
+}
+
+// Seat rent close_lobby returns per seat, in [team1..., team2...] order: one seat's rent (seat_size), what
+// internal_join_side collected per join (for a claimed rematch reservation, partly prepaid by the creator,
+// see Lobby::claim_reservation). The creator's seat gets none (the remainder goes to them), and
+// migrated lobbies collected none: the creator paid the whole account.
+fn seat_rent_refunds(lobby: &Lobby, rent: &Rent) -> Vec<u64> {
+    let seat_rent = if lobby.seat_rent_prepaid {
+        0
+    } else {
+        rent.minimum_balance(lobby.seat_size()).saturating_sub(rent.minimum_balance(0))
+    };
+    lobby.team1.iter().chain(lobby.team2.iter())
+        .map(|p| if *p == lobby.creator { 0 } else { seat_rent })
+        .collect()
+}
+
+// Extra ActiveLobby seed for `slot`. Slot 0 adds nothing, so it is the original ["active", creator] PDA
+// and lobbies created before slots existed keep working; slot N appends [N].
+fn active_slot_seed(slot: &u8) -> &[u8] {
+    if *slot == 0 { &[] } else { std::slice::from_ref(slot) }
+}
+
+// Allocates a program-owned PDA paid by `payer` (quick_play's conditional create). Same steps as Anchor's `init`:
+// create_account fails on an address that already holds lamports, so a pre-funded PDA is topped up to rent
+// exemption and then allocated and assigned instead (otherwise anyone could block the address with a transfer).
+fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    system_program_ai: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    use anchor_lang::system_program::{allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer};
+
+    let lamports = Rent::get()?.minimum_balance(space);
+    let current = target.lamports();
+    if current == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program_ai.clone(),
+                CreateAccount { from: payer.clone(), to: target.clone() },
+                &[signer_seeds],
+            ),
+            lamports,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    let shortfall = lamports.saturating_sub(current);
+    if shortfall > 0 {
+        transfer(
+            CpiContext::new(
+                system_program_ai.clone(),
+                Transfer { from: payer.clone(), to: target.clone() },
+            ),
+            shortfall,
+        )?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program_ai.clone(),
+            Allocate { account_to_allocate: target.clone() },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program_ai.clone(),
+            Assign { account_to_assign: target.clone() },
+            &[signer_seeds],
+        ),
+        &crate::ID,
+    )
+}
+
+// Orao accounts needed to request randomness (besides the request itself)
+#[derive(Clone)]
+struct OraoAccounts<'info> {
+    program: AccountInfo<'info>,
+    network_state: AccountInfo<'info>,
+    treasury: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+}
+impl<'info> OraoAccounts<'info> {
+    // Some only if the caller passed all three optional Orao accounts
+    fn from_optional(
+        program: &Option<Program<'info, OraoVrf>>,
+        network_state: &Option<Account<'info, NetworkState>>,
+        treasury: &Option<UncheckedAccount<'info>>,
+        system_program: AccountInfo<'info>,
+    ) -> Option<Self> {
+        Some(Self {
+            program: program.as_ref()?.to_account_info(),
+            network_state: network_state.as_ref()?.to_account_info(),
+            treasury: treasury.as_ref()?.to_account_info(),
+            system_program,
+        })
+    }
+}
+
+// VRF seed of a lobby: sha256(VRF_SEED_DOMAIN || lobby || lobby_id LE || nonce LE).
+// Bound to the lobby so no other lobby's (or an old) request can be reused; the nonce lets the
+// final joiner move on if someone squats the request PDA for a given seed.
+pub fn derive_vrf_seed(lobby: &Pubkey, lobby_id: u64, nonce: u64) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(VRF_SEED_DOMAIN);
+    hasher.update(lobby.as_ref());
+    hasher.update(lobby_id.to_le_bytes());
+    hasher.update(nonce.to_le_bytes());
+    hasher.finalize().into()
+}
+
+// Orao randomness request PDA for `seed`
+fn orao_request_address(seed: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[RANDOMNESS_ACCOUNT_SEED, seed], &ORAO_VRF_PROGRAM_ID).0
+}
+
+// Requests randomness from `provider` for a lobby that just filled and moves it to Pending.
+// `request` is the provider's randomness account:
+// - Orao: request PDA of derive_vrf_seed(lobby, lobby_id, vrf_nonce), created here; `payer` (the final joiner)
+//   pays the Orao fee
+// - Switchboard On-Demand: randomness account committed in the previous slot, controlled by `payer` (the final
+//   joiner). Its commit slot hash is stored as vrf_seed so re-committing the account later can't re-roll the
+//   lobby, and the payer's side as randomness_side: withdrawing the commit loses them the match (vrf_nonce unused)
+// - Commit-reveal: nothing to request (no account); Pending opens the reveal window
+fn request_randomness<'info>(
+    lobby: &mut Lobby,
+    lobby_key: Pubkey,
+    provider: RandomnessProvider,
+    payer: AccountInfo<'info>,
+    request: Option<AccountInfo<'info>>,
+    orao: Option<OraoAccounts<'info>>,
+    vrf_nonce: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let request_key = request.as_ref().map(|r| r.key()).unwrap_or_default();
+
+    match provider {
+        RandomnessProvider::Orao => {
+            let request = request.ok_or(PvpError::MissingProviderAccounts)?;
+            let orao = orao.ok_or(PvpError::MissingProviderAccounts)?;
+            let vrf_seed = derive_vrf_seed(&lobby_key, lobby.lobby_id, vrf_nonce);
+            require_keys_eq!(request_key, orao_request_address(&vrf_seed), PvpError::WrongRandomnessAccount);
+            lobby.vrf_seed = vrf_seed;
+
+            // Request randomness from Orao VRF using proper CPI (like in russian-roulette example)
+            let cpi_accounts = orao_solana_vrf::cpi::accounts::RequestV2 {
+                payer,
+                network_state: orao.network_state,
+                treasury: orao.treasury,
+                request,
+                system_program: orao.system_program,
+            };
+            let cpi_ctx = CpiContext::new(orao.program, cpi_accounts);
+            orao_solana_vrf::cpi::request_v2(cpi_ctx, vrf_seed)?;
+
+            msg!("VRF request sent to Orao network!");
+        }
+        RandomnessProvider::SwitchboardOnDemand => {
+            let request = request.ok_or(PvpError::MissingProviderAccounts)?;
+            let randomness = load_switchboard_randomness(&request)?;
+            check_switchboard_commit(&randomness, &payer.key(), clock.slot)?;
+            lobby.vrf_seed = randomness.seed_slothash;
+            lobby.randomness_side = if lobby.team1.contains(payer.key) { 0 } else { 1 };
+
+            msg!("Switchboard randomness committed at slot {}", randomness.seed_slot);
+        }
+        RandomnessProvider::CommitReveal => {
+            msg!("Reveal window open for {}s", REVEAL_WINDOW_SECS);
+        }
+    }
+
+    // Store the randomness account for later resolution
+    lobby.vrf_request = request_key;
+    lobby.randomness_provider = provider;
+
+    // Move to Pending - waiting for the provider to fulfill
+    lobby.status = LobbyStatus::Pending;
+    lobby.pending_since = clock.unix_timestamp;
+    Ok(())
+}
+
+// Whether a Switchboard randomness account may fill a lobby (final join by `payer`) in `slot`: committed in
+// the slot before, and controlled by the payer. Its authority can re-commit or close it while the lobby is
+// Pending; requiring the payer means whoever could withdraw a result is the one who loses by it (switchboard_outcome).
+fn check_switchboard_commit(randomness: &SwitchboardRandomness, payer: &Pubkey, slot: u64) -> Result<()> {
+    require!(randomness.committed_just_before(slot), PvpError::StaleRandomnessCommit);
+    require_keys_eq!(randomness.authority, *payer, PvpError::RandomnessAuthorityNotPayer);
+    Ok(())
+}
+
+// Whether `request` can serve the randomness request of lobby `lobby_key` under `provider` right now.
+// Lets quick_play skip filling candidates instead of failing in request_randomness.
+fn randomness_request_ok(
+    provider: RandomnessProvider,
+    lobby_key: &Pubkey,
+    lobby: &Lobby,
+    payer: &Pubkey,
+    vrf_nonce: u64,
+    request: &AccountInfo,
+    has_orao_accounts: bool,
+) -> bool {
+    match provider {
+        RandomnessProvider::Orao => {
+            has_orao_accounts
+                && request.key() == orao_request_address(&derive_vrf_seed(lobby_key, lobby.lobby_id, vrf_nonce))
+        }
+        RandomnessProvider::SwitchboardOnDemand => match (Clock::get(), load_switchboard_randomness(request)) {
+            (Ok(clock), Ok(randomness)) => check_switchboard_commit(&randomness, payer, clock.slot).is_ok(),
+            _ => false,
+        },
+        // quick_play never fills commit-reveal lobbies
+        RandomnessProvider::CommitReveal => false,
+    }
+}
+
+// Commitment a commit-reveal player submits on join: sha256(COMMIT_DOMAIN || lobby || player || secret).
+// Binding the lobby and player stops anyone from copying another player's commitment.
+pub fn commitment_hash(lobby: &Pubkey, player: &Pubkey, secret: &[u8; 32]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(COMMIT_DOMAIN);
+    hasher.update(lobby.as_ref());
+    hasher.update(player.as_ref());
+    hasher.update(secret);
+    hasher.finalize().into()
+}
+
+// Randomness of a fully revealed commit-reveal lobby:
+// sha512(REVEAL_RANDOMNESS_DOMAIN || lobby || secrets in seat order (team1..., team2...)).
+// One honest secret is enough to make it unpredictable to everyone else.
+pub fn reveal_randomness(lobby_key: &Pubkey, lobby: &Lobby) -> [u8; 64] {
+    use sha2::{Digest, Sha512};
+    let mut hasher = Sha512::new();
+    hasher.update(REVEAL_RANDOMNESS_DOMAIN);
+    hasher.update(lobby_key.as_ref());
+    for player in lobby.team1.iter().chain(lobby.team2.iter()) {
+        if let Some(seat) = lobby.commitments.iter().find(|c| c.player == *player) {
+            hasher.update(seat.value);
+        }
+    }
+    hasher.finalize().into()
+}
+
+// How resolve_match settles a Pending lobby
+enum Settlement {
+    Draw([u8; 64]), // winner drawn from provider randomness (commit-reveal: everyone revealed)
+    Forfeit,        // commit-reveal deadline passed with missing reveals: settle_forfeits
+    Withdrawn(u8),  // Switchboard commit re-committed / closed before settlement: this side (final joiner's) loses
+}
+
+// Everyone revealed: draw from the combined reveals. Otherwise wait for the deadline, then every player
+// who missed it forfeits their stake (forfeit_payouts).
+fn reveal_outcome(lobby_key: &Pubkey, lobby: &Lobby, now: i64) -> Result<Settlement> {
+    if lobby.missing_reveals().is_empty() {
+        return Ok(Settlement::Draw(reveal_randomness(lobby_key, lobby)));
+    }
+    require!(now > lobby.reveal_deadline(), PvpError::RevealWindowOpen);
+    Ok(Settlement::Forfeit)
+}
+
+// Payout of every seat (team1..., team2...) when reveals were missed: each revealer gets their stake back
+// plus an equal share of the non-revealers' stakes, non-revealers get nothing. If nobody revealed there is
+// nobody to forfeit to and everyone gets their stake back. Split dust stays in the PDA (creator, on close_lobby).
+fn forfeit_payouts(lobby: &Lobby) -> Vec<(Pubkey, u64)> {
+    let seats: Vec<(Pubkey, u64, bool)> = lobby.team1.iter().map(|p| (*p, lobby.stake_for_side(0)))
+        .chain(lobby.team2.iter().map(|p| (*p, lobby.stake_for_side(1))))
+        .map(|(p, stake)| (p, stake, lobby.has_revealed(&p)))
+        .collect();
+    let revealers = seats.iter().filter(|(_, _, revealed)| *revealed).count() as u64;
+    let forfeited: u64 = seats.iter().filter(|(_, _, revealed)| !*revealed).map(|(_, stake, _)| *stake).sum();
+    let share = forfeited.checked_div(revealers).unwrap_or(0);
+
+    seats.into_iter()
+        .map(|(player, stake, revealed)| {
+            let amount = match (revealers, revealed) {
+                (0, _) => stake,
+                (_, true) => stake + share,
+                (_, false) => 0,
+            };
+            (player, amount)
+        })
+        .collect()
+}
+
+// Winning side of a forfeit: the side of the revealers (1v1, see MAX_COMMIT_REVEAL_PLAYERS: the only revealer).
+// None if nobody revealed.
+fn forfeit_winner(lobby: &Lobby) -> Option<u8> {
+    if lobby.team1.iter().any(|p| lobby.has_revealed(p)) {
+        Some(0)
+    } else if lobby.team2.iter().any(|p| lobby.has_revealed(p)) {
+        Some(1)
+    } else {
+        None
+    }
+}
 
-// Handles the actual stake transfer and array push based on side (0 = team1, 1 = team2)
+// Handles the actual stake transfer and array push based on side (0 = team1, 1 = team2).
+// Commit-reveal lobbies take the player's commitment with the seat; other lobbies take none.
 fn internal_join_side<'info>(
     payer: &Signer<'info>,
-    lobby: &mut Account<'info, Lobby>,
+    lobby: &mut Lobby,
+    lobby_ai: AccountInfo<'info>,
     system_program_ai: AccountInfo<'info>,
     side: u8,
+    commitment: Option<[u8; 32]>,
 ) -> Result<()> {
     let payer_key = payer.key();
+    if lobby.randomness_provider == RandomnessProvider::CommitReveal {
+        require!(commitment.is_some(), PvpError::MissingCommitment);
+    } else {
+        require!(commitment.is_none(), PvpError::UnexpectedCommitment);
+    }
+
+    // No joins after expiry (the lobby is waiting for expire_refund)
+    let now = Clock::get()?.unix_timestamp;
+    require!(now < lobby.expires_at, PvpError::LobbyExpired);
+
+    // Rematch: seats are held for the previous players until reserved_until
+    if now < lobby.reserved_until {
+        require!(lobby.reserved.contains(&payer_key), PvpError::SeatReserved);
+    }
 
     // Предотвращаем дубль-join
     require!(
@@ -909,28 +3844,347 @@ fn internal_join_side<'info>(
 
     // Проверка слота
     match side {
-        0 => require!((lobby.team1.len() as u8) < lobby.team_size, PvpError::SideFull),
-        1 => require!((lobby.team2.len() as u8) < lobby.team_size, PvpError::SideFull),
+        0 => require!((lobby.team1.len() as u8) < lobby.team1_size, PvpError::SideFull),
+        1 => require!((lobby.team2.len() as u8) < lobby.team2_size, PvpError::SideFull),
         _ => return err!(PvpError::InvalidSide),
     }
 
-    // Перевод стейка на PDA лобби
-    let ix = system_instruction::transfer(&payer_key, &lobby.key(), lobby.stake_lamports);
+    // A previous player taking their rematch seat frees their reservation; its prepaid bytes become the seat.
+    lobby.claim_reservation(&payer_key);
+
+    // Grow the account by one seat if needed; the joiner pays that seat's rent.
+    // (The creator's seat is part of the initial allocation, so nothing to grow on create.)
+    let new_len = lobby.space_with_players(lobby.team1.len() + lobby.team2.len() + 1);
+    let old_len = lobby_ai.data_len();
+    let seat_rent = if new_len > old_len {
+        let rent = Rent::get()?;
+        rent.minimum_balance(new_len).saturating_sub(rent.minimum_balance(old_len))
+    } else {
+        0
+    };
+
+    // Перевод стейка (и ренты за место) на PDA лобби
+    let amount = lobby.stake_for_side(side).saturating_add(seat_rent);
+    let ix = system_instruction::transfer(&payer_key, &lobby_ai.key(), amount);
     anchor_lang::solana_program::program::invoke(
         &ix,
         &[
             payer.to_account_info(),
-            lobby.to_account_info(),
+            lobby_ai.clone(),
             system_program_ai,
         ],
     )?;
+    if new_len > old_len {
+        lobby_ai.realloc(new_len, false)?;
+    }
 
     // Добавляем игрока в сторону
     if side == 0 { lobby.team1.push(payer_key); } else { lobby.team2.push(payer_key); }
+    if let Some(value) = commitment {
+        lobby.commitments.push(SeatCommitment { player: payer_key, value, revealed: false });
+    }
+
+    Ok(())
+}
+
+// Reverse of internal_join_side: removes the player from their side, shrinks the account by one seat
+// and pays back the side's stake plus the freed seat rent. Returns (side, lamports paid back).
+fn internal_leave_side<'info>(
+    player: &AccountInfo<'info>,
+    lobby: &mut Account<'info, Lobby>,
+) -> Result<(u8, u64)> {
+    let player_key = player.key();
+    let side = if let Some(i) = lobby.team1.iter().position(|k| *k == player_key) {
+        lobby.team1.remove(i);
+        0
+    } else if let Some(i) = lobby.team2.iter().position(|k| *k == player_key) {
+        lobby.team2.remove(i);
+        1
+    } else {
+        return err!(PvpError::NotJoined);
+    };
+    lobby.commitments.retain(|c| c.player != player_key);
+
+    let lobby_ai = lobby.to_account_info();
+    let new_len = lobby.space_with_players(lobby.team1.len() + lobby.team2.len());
+    let old_len = lobby_ai.data_len();
+    let seat_rent = if new_len < old_len && !lobby.seat_rent_prepaid {
+        let rent = Rent::get()?;
+        lobby_ai.realloc(new_len, false)?;
+        rent.minimum_balance(old_len).saturating_sub(rent.minimum_balance(new_len))
+    } else {
+        0
+    };
+
+    let amount = lobby.stake_for_side(side).saturating_add(seat_rent);
+    **lobby_ai.try_borrow_mut_lamports()? -= amount;
+    **player.try_borrow_mut_lamports()? += amount;
+
+    Ok((side, amount))
+}
+
+// Authorizes a sensitive admin action and returns the role it was authorized with.
+// Council disabled: the admin signature is enough.
+// Council enabled: signer must be admin or a council member AND pass an unexecuted proposal
+// for exactly this action with >= threshold approvals from current members. The proposal is consumed.
+fn authorize_admin_action(
+    config: &GlobalConfig,
+    authority: &Pubkey,
+    proposal: Option<&mut Account<AdminProposal>>,
+    action: &AdminAction,
+) -> Result<Role> {
+    if !config.council_enabled() {
+        require!(*authority == config.admin, PvpError::Unauthorized);
+        return Ok(Role::Admin);
+    }
+
+    require!(
+        *authority == config.admin || config.is_council_member(authority),
+        PvpError::Unauthorized
+    );
+    let proposal = proposal.ok_or(PvpError::CouncilApprovalRequired)?;
+    require!(!proposal.executed, PvpError::ProposalAlreadyExecuted);
+    require!(proposal.action == *action, PvpError::ProposalActionMismatch);
+
+    let approvals = proposal.approvals.iter().filter(|k| config.is_council_member(k)).count();
+    require!(approvals >= config.council_threshold as usize, PvpError::ProposalNotApproved);
+
+    proposal.executed = true;
+    emit!(ProposalExecuted { proposal: proposal.key(), executor: *authority });
+    Ok(Role::Council)
+}
+
+// Loads the GameEntry for `game_key`.
+// Only this program can create GameEntry accounts, and it does so at the PDA of the key,
+// so a program-owned entry carrying the same key is the registered one.
+fn load_game_entry(game_entry_ai: &AccountInfo, game_key: GameKey) -> Result<GameEntry> {
+    require!(
+        game_entry_ai.owner == &crate::ID && !game_entry_ai.data_is_empty(),
+        PvpError::GameNotRegistered
+    );
+    let data = game_entry_ai.try_borrow_data()?;
+    let entry = GameEntry::try_deserialize(&mut &data[..])
+        .map_err(|_| error!(PvpError::GameNotRegistered))?;
+    require!(entry.game_key == game_key, PvpError::GameNotRegistered);
+    Ok(entry)
+}
+
+// True if `grant` is an active RoleGrant of `role` held by `authority` that still counts under the council.
+fn has_role(config: &GlobalConfig, grant: Option<&Account<RoleGrant>>, authority: &Pubkey, role: Role) -> bool {
+    grant.is_some_and(|g| {
+        g.active && g.role == role && g.grantee == *authority && grant_survives_council(config, g.role, g.granted_at)
+    })
+}
+
+// With the council enabled, only grants made after it was enabled (i.e. approved by council proposal) count,
+// so a role the admin handed out alone can't bypass M-of-N. Pauser is exempt: it can only pause.
+fn grant_survives_council(config: &GlobalConfig, role: Role, granted_at: i64) -> bool {
+    !config.council_enabled() || role == Role::Pauser || granted_at > config.council_since
+}
+
+// Refunds every participant their side stake and marks the lobby Refunded.
+// `participants` must be [team1..., team2...] in lobby order. Returns (refunded_count, total_refunded).
+// `withhold` (cranker reward) is split across the seats by Lobby::refund_for_side.
+fn refund_participants<'info>(
+    lobby: &mut Account<'info, Lobby>,
+    system_program_ai: AccountInfo<'info>,
+    participants: &[AccountInfo<'info>],
+    withhold: u64,
+) -> Result<(u8, u64)> {
+    require!(!lobby.finalized, PvpError::AlreadyFinalized);
+
+    // Save all values before mutable borrow
+    let team1_stake = lobby.refund_for_side(0, withhold);
+    let team2_stake = lobby.refund_for_side(1, withhold);
+    let lobby_creator = lobby.creator;
+    let lobby_id = lobby.lobby_id;
+    let lobby_bump = lobby.bump;
+    let team1_players: Vec<Pubkey> = lobby.team1.clone();
+    let team2_players: Vec<Pubkey> = lobby.team2.clone();
+    let total = team1_players.len() + team2_players.len();
+    require!(participants.len() == total, PvpError::BadRemainingAccounts);
+
+    // Mark finalized and change status - must do this before transfers to avoid reentrancy
+    lobby.finalized = true;
+    lobby.status = LobbyStatus::Refunded;
+
+    let from_ai = lobby.to_account_info();
+    let seats = team1_players.iter().map(|p| (p, team1_stake))
+        .chain(team2_players.iter().map(|p| (p, team2_stake)));
+    for ((p, stake), to_ai) in seats.zip(participants.iter()) {
+        require!(to_ai.key() == *p, PvpError::Unauthorized);
+        pay_from_lobby_pda(
+            lobby_creator,
+            lobby_id,
+            lobby_bump,
+            system_program_ai.clone(),
+            from_ai.clone(),
+            to_ai.clone(),
+            stake
+        )?;
+    }
+
+    let total_refunded = team1_stake * team1_players.len() as u64 + team2_stake * team2_players.len() as u64;
+    Ok((total as u8, total_refunded))
+}
+
+// Commit-reveal settlement after missed reveals: pays forfeit_payouts. The lobby is Resolved in favour of
+// the revealer's side (forfeit_winner), or Refunded if nobody revealed. Returns the payouts.
+fn settle_forfeits<'info>(
+    lobby: &mut Account<'info, Lobby>,
+    system_program_ai: AccountInfo<'info>,
+    participants: &[AccountInfo<'info>],
+    now: i64,
+) -> Result<Vec<(Pubkey, u64)>> {
+    require!(!lobby.finalized, PvpError::AlreadyFinalized);
+
+    let payouts = forfeit_payouts(lobby);
+    require!(participants.len() == payouts.len(), PvpError::BadRemainingAccounts);
+
+    // Mark finalized and change status - must do this before transfers
+    lobby.finalized = true;
+    match forfeit_winner(lobby) {
+        Some(side) => {
+            lobby.winner_side = side;
+            lobby.status = LobbyStatus::Resolved;
+            lobby.settled_at = now;
+        }
+        None => lobby.status = LobbyStatus::Refunded,
+    }
 
+    let from_ai = lobby.to_account_info();
+    for ((player, amount), to_ai) in payouts.iter().zip(participants.iter()) {
+        require!(to_ai.key() == *player, PvpError::RemainingAccountsMismatch);
+        pay_from_lobby_pda(
+            lobby.creator,
+            lobby.lobby_id,
+            lobby.bump,
+            system_program_ai.clone(),
+            from_ai.clone(),
+            to_ai.clone(),
+            *amount
+        )?;
+    }
+
+    Ok(payouts)
+}
+
+// Permissionless refund: refunds everyone minus the cranker reward (withheld pro rata),
+// pays that reward to `cranker` and emits LobbyRefunded.
+fn cranked_refund<'info>(
+    lobby: &mut Account<'info, Lobby>,
+    config: &GlobalConfig,
+    system_program_ai: AccountInfo<'info>,
+    cranker: AccountInfo<'info>,
+    participants: &[AccountInfo<'info>],
+) -> Result<()> {
+    let pot = lobby.pot();
+    let (refunded_count, total_refunded) = refund_participants(
+        lobby,
+        system_program_ai.clone(),
+        participants,
+        config.crank_reward_for(pot),
+    )?;
+    let crank_reward = pot - total_refunded;
+    pay_from_lobby_pda(
+        lobby.creator,
+        lobby.lobby_id,
+        lobby.bump,
+        system_program_ai,
+        lobby.to_account_info(),
+        cranker.clone(),
+        crank_reward
+    )?;
+
+    emit!(LobbyRefunded {
+        lobby: lobby.key(),
+        refunded_count,
+        total_refunded,
+        cranker: cranker.key(),
+        crank_reward,
+    });
     Ok(())
 }
 
+// Decodes an Orao randomness request account through the Orao crate's typed state.
+// V1 (Randomness) and V2 (RandomnessV2) are told apart by their account discriminators;
+// anything else is rejected instead of being read at guessed offsets.
+fn load_orao_randomness(vrf_request: &AccountInfo) -> Result<RandomnessAccountData> {
+    require!(vrf_request.owner == &ORAO_VRF_PROGRAM_ID, PvpError::WrongRandomnessAccount);
+    let data = vrf_request.try_borrow_data()?;
+    require!(data.len() >= 8, PvpError::InvalidRandomnessData);
+
+    let discriminator = &data[..8];
+    if discriminator == RandomnessV2::DISCRIMINATOR {
+        let v2 = RandomnessV2::try_deserialize(&mut &data[..]).map_err(|_| error!(PvpError::InvalidRandomnessData))?;
+        Ok(RandomnessAccountData::V2(v2))
+    } else if discriminator == Randomness::DISCRIMINATOR {
+        let v1 = Randomness::try_deserialize(&mut &data[..]).map_err(|_| error!(PvpError::InvalidRandomnessData))?;
+        Ok(RandomnessAccountData::V1(v1))
+    } else {
+        err!(PvpError::UnknownRandomnessVersion)
+    }
+}
+
+// Fulfilled 64-byte randomness of a lobby's request. The request must have been made with `expected_seed`.
+fn read_vrf_randomness(vrf_request: &AccountInfo, expected_seed: &[u8; 32]) -> Result<[u8; 64]> {
+    let randomness = load_orao_randomness(vrf_request)?;
+    require!(randomness.seed() == expected_seed, PvpError::RandomnessSeedMismatch);
+    randomness.fulfilled_randomness().copied().ok_or(error!(PvpError::RandomnessNotFulfilled))
+}
+
+// Reads a Switchboard On-Demand randomness account (owner and discriminator checked)
+fn load_switchboard_randomness(randomness_ai: &AccountInfo) -> Result<SwitchboardRandomness> {
+    require!(randomness_ai.owner == &switchboard::PROGRAM_ID, PvpError::WrongRandomnessAccount);
+    let data = randomness_ai.try_borrow_data()?;
+    SwitchboardRandomness::parse(&data).ok_or(error!(PvpError::InvalidRandomnessData))
+}
+
+// How an oracle lobby settles from its randomness account (`vrf_request`, already matched to lobby.vrf_request).
+// Errors while there is nothing to settle with yet.
+fn oracle_outcome(lobby: &Lobby, vrf_request: &AccountInfo) -> Result<Settlement> {
+    match lobby.randomness_provider {
+        RandomnessProvider::Orao => Ok(Settlement::Draw(read_vrf_randomness(vrf_request, &lobby.vrf_seed)?)),
+        // Closed or re-created elsewhere: no longer a readable randomness account
+        RandomnessProvider::SwitchboardOnDemand => {
+            switchboard_outcome(lobby, load_switchboard_randomness(vrf_request).ok().as_ref())
+        }
+        // No randomness account: resolve_match reads the reveals (reveal_outcome)
+        RandomnessProvider::CommitReveal => err!(PvpError::WrongRandomnessAccount),
+    }
+}
+
+// Switchboard: the revealed value of the lobby's commit, as the 64 bytes RngStream takes (the 32-byte value fills
+// the first half, the rest stays zero). It is readable from the reveal on, not only in the reveal slot, so nobody
+// can dodge a known result by not settling in that slot. Once the lobby's commit is no longer the account's
+// (re-committed, or `randomness` None: closed), its authority - the final joiner - withdrew it, possibly after
+// seeing a losing value: their side loses.
+fn switchboard_outcome(lobby: &Lobby, randomness: Option<&SwitchboardRandomness>) -> Result<Settlement> {
+    match randomness {
+        Some(r) if r.unrevealed(&lobby.vrf_seed) => err!(PvpError::RandomnessNotFulfilled),
+        Some(r) => match r.revealed_value(&lobby.vrf_seed) {
+            Some(value) => {
+                let mut bytes = [0u8; 64];
+                bytes[..32].copy_from_slice(&value);
+                Ok(Settlement::Draw(bytes))
+            }
+            None => Ok(Settlement::Withdrawn(lobby.randomness_side)),
+        },
+        None => Ok(Settlement::Withdrawn(lobby.randomness_side)),
+    }
+}
+
+// Whether resolve_match can settle a Pending lobby, in which case it must not be refunded. Orao requests
+// can't be re-rolled, so only a fulfilled request settles. Switchboard is unsettled only while the lobby's
+// commit is still the account's current one and unrevealed (anything else settles, see switchboard_outcome).
+// Commit-reveal lobbies always settle through resolve_match, forfeits included.
+fn randomness_settleable(lobby: &Lobby, vrf_request: &AccountInfo) -> bool {
+    match lobby.randomness_provider {
+        RandomnessProvider::CommitReveal => true,
+        _ => oracle_outcome(lobby, vrf_request).is_ok(),
+    }
+}
+
 // Transfer lamports from the lobby PDA to the given account.
 // The `to` AccountInfo must be present in the instruction's account list (remaining_accounts).
 // NOTE: For accounts with data, we cannot use system_instruction::transfer
@@ -954,12 +4208,649 @@ fn pay_from_lobby_pda<'info>(
     Ok(())
 }
 
-// Compute the 8-byte Anchor discriminator for our callback method name.
-// This is used to build Switchboard's on-chain Callback ix_data payload.
-fn pvp_ix_discriminator(name: &str) -> Vec<u8> {
-    // Anchor discriminator is: sha256("global::<name>")[0..8]
-    use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    hasher.update(format!("global::{}", name));
-    hasher.finalize()[..8].to_vec()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lobby_with(randomness_provider: RandomnessProvider, team1_size: u8, team2_size: u8, stake_lamports: u64) -> Lobby {
+        let params = NewLobby {
+            team1_size,
+            team2_size,
+            stake_lamports,
+            game_key: GameKey { game: 0, game_mode: 0, arena_type: 0 },
+            expires_at: 1_000,
+            active_slot: 0,
+            randomness_provider,
+        };
+        new_open_lobby(255, 1, Pubkey::new_unique(), &params, 0, 0)
+    }
+
+    fn lobby(team1_size: u8, team2_size: u8, stake_lamports: u64) -> Lobby {
+        lobby_with(RandomnessProvider::Orao, team1_size, team2_size, stake_lamports)
+    }
+
+    // Seats `players` new players on `side` (with a commitment each in commit-reveal lobbies)
+    fn seat(lobby: &mut Lobby, side: u8, players: usize) {
+        for _ in 0..players {
+            let player = Pubkey::new_unique();
+            let team = if side == 0 { &mut lobby.team1 } else { &mut lobby.team2 };
+            team.push(player);
+            if lobby.randomness_provider == RandomnessProvider::CommitReveal {
+                lobby.commitments.push(SeatCommitment { player, value: [7u8; 32], revealed: false });
+            }
+        }
+    }
+
+    fn serialized_len(lobby: &Lobby) -> usize {
+        let mut data = Vec::new();
+        lobby.try_serialize(&mut data).unwrap();
+        data.len()
+    }
+
+    fn config(crank_reward_bps: u16, max_crank_reward_lamports: u64) -> GlobalConfig {
+        GlobalConfig {
+            bump: 255,
+            admin: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            fee_bps: 0,
+            min_stake_lamports: 0,
+            refund_lock_secs: 0,
+            vrf_timeout_secs: 0,
+            force_refund_min_stuck_secs: 0,
+            crank_reward_bps,
+            max_crank_reward_lamports,
+            min_lobby_ttl_secs: 0,
+            max_lobby_ttl_secs: 0,
+            max_active_lobbies: 1,
+            randomness_provider: RandomnessProvider::Orao,
+            pause_flags: 0,
+            pending_admin: Pubkey::default(),
+            council_threshold: 0,
+            council_since: 0,
+            council: Vec::new(),
+        }
+    }
+
+    // Runs `f` against a program-owned AdminProposal account for `action` approved by `approvals`
+    fn with_proposal<R>(
+        action: AdminAction,
+        approvals: Vec<Pubkey>,
+        f: impl FnOnce(&mut Account<AdminProposal>) -> R,
+    ) -> R {
+        let proposal = AdminProposal {
+            bump: 255,
+            proposal_id: 1,
+            proposer: Pubkey::new_unique(),
+            action,
+            approvals,
+            created_at: 0,
+            executed: false,
+        };
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = Vec::new();
+        proposal.try_serialize(&mut data).unwrap();
+        let ai = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &crate::ID, false, 0);
+        f(&mut Account::try_from(&ai).unwrap())
+    }
+
+    // 2-of-3 council
+    fn council_config() -> GlobalConfig {
+        let mut c = config(0, 0);
+        c.council = (0..3).map(|_| Pubkey::new_unique()).collect();
+        c.council_threshold = 2;
+        c
+    }
+
+    fn grant(grantee: Pubkey) -> AdminAction {
+        AdminAction::GrantRole { role: Role::Pauser, grantee }
+    }
+
+    #[test]
+    fn admin_acts_alone_without_a_council() {
+        let c = config(0, 0);
+        let action = grant(Pubkey::new_unique());
+        assert_eq!(authorize_admin_action(&c, &c.admin, None, &action).unwrap(), Role::Admin);
+        assert_eq!(
+            authorize_admin_action(&c, &Pubkey::new_unique(), None, &action).unwrap_err(),
+            PvpError::Unauthorized.into()
+        );
+    }
+
+    #[test]
+    fn council_rejects_the_admin_alone() {
+        let c = council_config();
+        let action = grant(Pubkey::new_unique());
+        assert_eq!(
+            authorize_admin_action(&c, &c.admin, None, &action).unwrap_err(),
+            PvpError::CouncilApprovalRequired.into()
+        );
+    }
+
+    #[test]
+    fn council_rejects_outsiders_even_with_a_quorum() {
+        let c = council_config();
+        let action = grant(Pubkey::new_unique());
+        with_proposal(action.clone(), c.council[..2].to_vec(), |p| {
+            assert_eq!(
+                authorize_admin_action(&c, &Pubkey::new_unique(), Some(p), &action).unwrap_err(),
+                PvpError::Unauthorized.into()
+            );
+            assert!(!p.executed);
+        });
+    }
+
+    #[test]
+    fn council_needs_threshold_approvals_from_current_members() {
+        let c = council_config();
+        let action = grant(Pubkey::new_unique());
+        // one member plus a non-member (e.g. a removed signer) is still below 2-of-3
+        with_proposal(action.clone(), vec![c.council[0], Pubkey::new_unique()], |p| {
+            assert_eq!(
+                authorize_admin_action(&c, &c.council[0], Some(p), &action).unwrap_err(),
+                PvpError::ProposalNotApproved.into()
+            );
+        });
+        with_proposal(action.clone(), c.council[1..].to_vec(), |p| {
+            assert_eq!(authorize_admin_action(&c, &c.council[0], Some(p), &action).unwrap(), Role::Council);
+            assert!(p.executed);
+        });
+    }
+
+    #[test]
+    fn council_proposal_is_single_use_and_action_bound() {
+        let c = council_config();
+        let action = grant(Pubkey::new_unique());
+        with_proposal(action.clone(), c.council.clone(), |p| {
+            assert_eq!(
+                authorize_admin_action(&c, &c.admin, Some(p), &grant(Pubkey::new_unique())).unwrap_err(),
+                PvpError::ProposalActionMismatch.into()
+            );
+            authorize_admin_action(&c, &c.admin, Some(p), &action).unwrap();
+            assert_eq!(
+                authorize_admin_action(&c, &c.admin, Some(p), &action).unwrap_err(),
+                PvpError::ProposalAlreadyExecuted.into()
+            );
+        });
+    }
+
+    #[test]
+    fn game_shape_cap_fits_every_allowed_orientation() {
+        let orientations: usize = ALLOWED_TEAM_SHAPES.iter().map(|(lo, hi)| if lo == hi { 1 } else { 2 }).sum();
+        assert_eq!(orientations, MAX_GAME_SHAPES);
+    }
+
+    #[test]
+    fn stake_for_side_is_flat_for_symmetric_lobbies() {
+        let l = lobby(2, 2, 100);
+        assert_eq!(l.stake_for_side(0), 100);
+        assert_eq!(l.stake_for_side(1), 100);
+    }
+
+    #[test]
+    fn stake_for_side_scales_the_smaller_side() {
+        let l = lobby(1, 10, 100);
+        assert_eq!(l.stake_for_side(0), 1_000);
+        assert_eq!(l.stake_for_side(1), 100);
+
+        let l = lobby(10, 1, 100);
+        assert_eq!(l.stake_for_side(0), 100);
+        assert_eq!(l.stake_for_side(1), 1_000);
+
+        let l = lobby(2, 20, 100);
+        assert_eq!(l.stake_for_side(0), 1_000);
+
+        let l = lobby(4, 40, 100);
+        assert_eq!(l.stake_for_side(0), 1_000);
+    }
+
+    #[test]
+    fn stake_for_side_saturates() {
+        let l = lobby(1, 10, u64::MAX / 2);
+        assert_eq!(l.stake_for_side(0), u64::MAX);
+    }
+
+    #[test]
+    fn pot_matches_both_sides_when_full() {
+        let mut l = lobby(1, 10, 100);
+        seat(&mut l, 0, 1);
+        seat(&mut l, 1, 10);
+        assert!(l.is_full());
+        assert_eq!(l.pot(), 2_000);
+
+        let mut l = lobby(2, 2, 100);
+        seat(&mut l, 0, 2);
+        seat(&mut l, 1, 2);
+        assert_eq!(l.pot(), 400);
+    }
+
+    #[test]
+    fn pot_counts_only_seated_players() {
+        let mut l = lobby(1, 10, 100);
+        assert_eq!(l.pot(), 0);
+        seat(&mut l, 1, 3);
+        assert_eq!(l.pot(), 300);
+        seat(&mut l, 0, 1);
+        assert_eq!(l.pot(), 1_300);
+    }
+
+    #[test]
+    fn joins_grow_the_account_one_seat_at_a_time() {
+        let mut l = lobby(1, 10, 100);
+        assert_eq!(serialized_len(&l), Lobby::space_for_seats(0));
+        for players in 1..=11 {
+            seat(&mut l, if players == 1 { 0 } else { 1 }, 1);
+            assert_eq!(serialized_len(&l), Lobby::space_for_seats(players));
+            assert_eq!(l.space_with_players(players) - l.space_with_players(players - 1), Lobby::PER_PLAYER);
+        }
+    }
+
+    #[test]
+    fn pick_side_passes_explicit_sides_through() {
+        let mut l = lobby(2, 2, 100);
+        seat(&mut l, 0, 2);
+        assert_eq!(l.pick_side(0), 0);
+        assert_eq!(l.pick_side(1), 1);
+    }
+
+    #[test]
+    fn pick_side_any_prefers_team1_on_ties() {
+        let mut l = lobby(2, 2, 100);
+        assert_eq!(l.pick_side(SIDE_ANY), 0);
+        seat(&mut l, 0, 1);
+        assert_eq!(l.pick_side(SIDE_ANY), 1);
+        seat(&mut l, 1, 1);
+        assert_eq!(l.pick_side(SIDE_ANY), 0);
+    }
+
+    #[test]
+    fn pick_side_any_balances_by_fill_ratio() {
+        let mut l = lobby(1, 10, 100);
+        assert_eq!(l.pick_side(SIDE_ANY), 0);
+        seat(&mut l, 0, 1);
+        assert_eq!(l.pick_side(SIDE_ANY), 1);
+
+        // 0/1 seated on team1 vs 5/10 on team2: team1 is emptier
+        let mut l = lobby(1, 10, 100);
+        seat(&mut l, 1, 5);
+        assert_eq!(l.pick_side(SIDE_ANY), 0);
+    }
+
+    fn quick_play_fits(l: &Lobby, player: &Pubkey, now: i64) -> bool {
+        l.matches_quick_play(l.game_key, l.team1_size, l.team2_size, l.stake_lamports, player, now)
+    }
+
+    #[test]
+    fn quick_play_takes_an_open_matching_lobby() {
+        let mut l = lobby(2, 2, 100);
+        seat(&mut l, 0, 1);
+        assert!(quick_play_fits(&l, &Pubkey::new_unique(), 0));
+    }
+
+    #[test]
+    fn quick_play_skips_other_games_shapes_and_stakes() {
+        let l = lobby(2, 2, 100);
+        let player = Pubkey::new_unique();
+        let other_game = GameKey { game: 1, ..l.game_key };
+        assert!(!l.matches_quick_play(other_game, 2, 2, 100, &player, 0));
+        assert!(!l.matches_quick_play(l.game_key, 1, 1, 100, &player, 0));
+        assert!(!l.matches_quick_play(l.game_key, 2, 2, 200, &player, 0));
+    }
+
+    #[test]
+    fn quick_play_skips_commit_reveal_lobbies() {
+        let l = lobby_with(RandomnessProvider::CommitReveal, 1, 1, 100);
+        assert!(!quick_play_fits(&l, &Pubkey::new_unique(), 0));
+    }
+
+    #[test]
+    fn quick_play_skips_unseatable_lobbies() {
+        let player = Pubkey::new_unique();
+
+        let mut full = lobby(1, 1, 100);
+        seat(&mut full, 0, 1);
+        seat(&mut full, 1, 1);
+        assert!(!quick_play_fits(&full, &player, 0));
+
+        let mut pending = lobby(2, 2, 100);
+        pending.status = LobbyStatus::Pending;
+        assert!(!quick_play_fits(&pending, &player, 0));
+
+        let l = lobby(2, 2, 100);
+        assert!(!quick_play_fits(&l, &player, l.expires_at));
+
+        let mut joined = lobby(2, 2, 100);
+        joined.team1.push(player);
+        assert!(!quick_play_fits(&joined, &player, 0));
+    }
+
+    #[test]
+    fn quick_play_respects_rematch_reservations() {
+        let previous = Pubkey::new_unique();
+        let mut l = lobby(2, 2, 100);
+        l.reserved = vec![previous];
+        l.reserved_until = 60;
+        assert!(!quick_play_fits(&l, &Pubkey::new_unique(), 59));
+        assert!(quick_play_fits(&l, &previous, 59));
+        assert!(quick_play_fits(&l, &Pubkey::new_unique(), 60));
+    }
+
+    #[test]
+    fn crank_reward_disabled_at_zero_bps() {
+        assert_eq!(config(0, u64::MAX).crank_reward_for(1_000_000), 0);
+    }
+
+    #[test]
+    fn crank_reward_is_a_share_of_the_pot() {
+        // 0.5% of 1_000_000
+        assert_eq!(config(50, u64::MAX).crank_reward_for(1_000_000), 5_000);
+        // rounds down for small pots
+        assert_eq!(config(50, u64::MAX).crank_reward_for(199), 0);
+    }
+
+    #[test]
+    fn crank_reward_is_capped() {
+        assert_eq!(config(50, 1_000).crank_reward_for(1_000_000), 1_000);
+    }
+
+    #[test]
+    fn crank_reward_does_not_overflow() {
+        let reward = config(10_000, u64::MAX).crank_reward_for(u64::MAX);
+        assert_eq!(reward, u64::MAX);
+        let reward = config(50, u64::MAX).crank_reward_for(u64::MAX);
+        assert_eq!(reward, (u64::MAX as u128 * 50 / 10_000) as u64);
+    }
+
+    #[test]
+    fn refunds_withhold_the_reward_pro_rata() {
+        // 1v10 at 100: team1 staked 1_000, each team2 seat 100; pot 2_000
+        let mut l = lobby(1, 10, 100);
+        seat(&mut l, 0, 1);
+        seat(&mut l, 1, 10);
+        assert_eq!(l.refund_for_side(0, 0), 1_000);
+        assert_eq!(l.refund_for_side(0, 20), 990);
+        assert_eq!(l.refund_for_side(1, 20), 99);
+    }
+
+    #[test]
+    fn refunds_never_withhold_more_than_the_reward() {
+        let mut l = lobby(2, 2, 100);
+        seat(&mut l, 0, 2);
+        seat(&mut l, 1, 1);
+        for withhold in [0, 1, 7, 299, 300, u64::MAX] {
+            let refunded = l.refund_for_side(0, withhold) * 2 + l.refund_for_side(1, withhold);
+            assert!(l.pot() - refunded <= withhold);
+        }
+        // a seated player is never charged more than their own stake
+        assert_eq!(l.refund_for_side(0, u64::MAX), 0);
+    }
+
+    #[test]
+    fn vrf_seed_changes_with_every_input() {
+        let lobby = Pubkey::new_unique();
+        let seed = derive_vrf_seed(&lobby, 1, 0);
+        assert_eq!(seed, derive_vrf_seed(&lobby, 1, 0));
+        assert_ne!(seed, derive_vrf_seed(&lobby, 1, 1));
+        assert_ne!(seed, derive_vrf_seed(&lobby, 2, 0));
+        assert_ne!(seed, derive_vrf_seed(&Pubkey::new_unique(), 1, 0));
+    }
+
+    #[test]
+    fn vrf_seeds_are_unique_across_nonces_lobbies_and_ids() {
+        let lobbies: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut seeds = std::collections::HashSet::new();
+        for lobby in &lobbies {
+            for lobby_id in 0..4 {
+                for nonce in [0, 1, 2, u64::MAX] {
+                    assert!(seeds.insert(derive_vrf_seed(lobby, lobby_id, nonce)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn space_with_players_matches_serialized_size() {
+        for provider in [RandomnessProvider::Orao, RandomnessProvider::CommitReveal] {
+            for reserved in 0..3 {
+                let mut l = lobby_with(provider, 2, 2, 100);
+                l.reserved = (0..reserved).map(|_| Pubkey::new_unique()).collect();
+                assert_eq!(serialized_len(&l), l.space_with_players(0));
+                for players in 1..=4 {
+                    seat(&mut l, (players % 2) as u8, 1);
+                    assert_eq!(serialized_len(&l), l.space_with_players(players));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn commit_reveal_rematch_fits_after_the_creator_joins() {
+        // Rematch of a resolved 1v1: created with space_for_seats(2), reservation recorded before the
+        // creator's join, which grows the account to space_with_players(1)
+        let created_with = Lobby::space_for_seats(2);
+        let mut l = lobby_with(RandomnessProvider::CommitReveal, 1, 1, 100);
+        l.reserved = vec![Pubkey::new_unique()];
+        let after_join = created_with.max(l.space_with_players(1));
+        seat(&mut l, 0, 1);
+        assert_eq!(serialized_len(&l), after_join);
+
+        // The opponent's join grows it by exactly one commit-reveal seat
+        seat(&mut l, 1, 1);
+        assert_eq!(serialized_len(&l), after_join + l.seat_size());
+    }
+
+    #[test]
+    fn oracle_rematch_needs_no_growth_for_the_creator() {
+        let created_with = Lobby::space_for_seats(4);
+        let mut l = lobby(2, 2, 100);
+        l.reserved = (0..3).map(|_| Pubkey::new_unique()).collect();
+        assert_eq!(l.space_with_players(1), created_with);
+        seat(&mut l, 0, 1);
+        assert_eq!(serialized_len(&l), created_with);
+    }
+
+    #[test]
+    fn reserved_seat_reuses_the_prepaid_reservation() {
+        for provider in [RandomnessProvider::Orao, RandomnessProvider::CommitReveal] {
+            let mut l = lobby_with(provider, 2, 2, 100);
+            let previous: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+            l.reserved = previous.clone();
+            seat(&mut l, 0, 1);
+            let before = serialized_len(&l);
+
+            assert!(l.claim_reservation(&previous[1]));
+            l.team2.push(previous[1]);
+            if provider == RandomnessProvider::CommitReveal {
+                l.commitments.push(SeatCommitment { player: previous[1], value: [7u8; 32], revealed: false });
+            }
+            assert_eq!(l.reserved, vec![previous[0], previous[2]]);
+            assert_eq!(serialized_len(&l), l.space_with_players(2));
+            // The joiner pays only for the bytes beyond the reservation (none for oracle seats)
+            assert_eq!(serialized_len(&l) - before, l.seat_size() - Lobby::PER_PLAYER);
+        }
+    }
+
+    #[test]
+    fn unreserved_players_claim_nothing() {
+        let mut l = lobby(2, 2, 100);
+        l.reserved = vec![Pubkey::new_unique()];
+        assert!(!l.claim_reservation(&Pubkey::new_unique()));
+        assert_eq!(l.reserved.len(), 1);
+    }
+
+    // Full lobby with the creator on team1
+    fn full_with_creator(provider: RandomnessProvider) -> Lobby {
+        let mut l = lobby_with(provider, 2, 2, 100);
+        l.team1.push(l.creator);
+        if provider == RandomnessProvider::CommitReveal {
+            l.commitments.push(SeatCommitment { player: l.creator, value: [7u8; 32], revealed: false });
+        }
+        seat(&mut l, 0, 1);
+        seat(&mut l, 1, 2);
+        l
+    }
+
+    #[test]
+    fn close_refunds_joiners_their_seat_rent() {
+        let rent = Rent::default();
+        for provider in [RandomnessProvider::Orao, RandomnessProvider::CommitReveal] {
+            let l = full_with_creator(provider);
+            let seat_rent = rent.minimum_balance(l.seat_size()) - rent.minimum_balance(0);
+            assert_eq!(seat_rent_refunds(&l, &rent), vec![0, seat_rent, seat_rent, seat_rent]);
+
+            // What's left is what the creator paid: header + their own seat
+            let collected = rent.minimum_balance(l.space_with_players(4));
+            let refunded: u64 = seat_rent_refunds(&l, &rent).iter().sum();
+            assert_eq!(collected - refunded, rent.minimum_balance(l.space_with_players(1)));
+        }
+    }
+
+    #[test]
+    fn close_refunds_no_seat_rent_for_migrated_lobbies() {
+        let mut l = full_with_creator(RandomnessProvider::Orao);
+        l.seat_rent_prepaid = true;
+        assert_eq!(seat_rent_refunds(&l, &Rent::default()), vec![0; 4]);
+    }
+
+    // Full, Pending 1v1 Switchboard lobby filled by team2 (the final joiner) with a commit against [9; 32]
+    fn pending_switchboard() -> Lobby {
+        let mut l = lobby_with(RandomnessProvider::SwitchboardOnDemand, 1, 1, 100);
+        seat(&mut l, 0, 1);
+        seat(&mut l, 1, 1);
+        l.status = LobbyStatus::Pending;
+        l.vrf_seed = [9u8; 32];
+        l.randomness_side = 1;
+        l
+    }
+
+    fn switchboard_account(authority: Pubkey, seed_slothash: [u8; 32], seed_slot: u64, reveal_slot: u64) -> SwitchboardRandomness {
+        SwitchboardRandomness { authority, seed_slothash, seed_slot, reveal_slot, value: [5u8; 32] }
+    }
+
+    #[test]
+    fn switchboard_commit_must_be_controlled_by_the_final_joiner() {
+        let payer = Pubkey::new_unique();
+        assert!(check_switchboard_commit(&switchboard_account(payer, [9u8; 32], 99, 0), &payer, 100).is_ok());
+        // a fresh keypair the final joiner doesn't control could be withdrawn on their opponent's behalf
+        let outsider = switchboard_account(Pubkey::new_unique(), [9u8; 32], 99, 0);
+        assert_eq!(
+            check_switchboard_commit(&outsider, &payer, 100).unwrap_err(),
+            PvpError::RandomnessAuthorityNotPayer.into()
+        );
+        assert!(check_switchboard_commit(&switchboard_account(payer, [9u8; 32], 98, 0), &payer, 100).is_err());
+    }
+
+    #[test]
+    fn switchboard_unrevealed_commit_waits() {
+        let l = pending_switchboard();
+        let account = switchboard_account(Pubkey::new_unique(), l.vrf_seed, 99, 0);
+        assert!(switchboard_outcome(&l, Some(&account)).is_err());
+    }
+
+    #[test]
+    fn switchboard_revealed_commit_draws() {
+        let l = pending_switchboard();
+        let account = switchboard_account(Pubkey::new_unique(), l.vrf_seed, 99, 101);
+        let Ok(Settlement::Draw(bytes)) = switchboard_outcome(&l, Some(&account)) else { panic!("expected a draw") };
+        assert_eq!(bytes[..32], [5u8; 32]);
+        assert_eq!(bytes[32..], [0u8; 32]);
+    }
+
+    #[test]
+    fn switchboard_recommit_loses_for_the_final_joiner() {
+        let l = pending_switchboard();
+        // revealed (a value the final joiner didn't like), then committed again against a new slot hash
+        for recommitted in [
+            switchboard_account(Pubkey::new_unique(), [1u8; 32], 150, 0),
+            switchboard_account(Pubkey::new_unique(), [1u8; 32], 150, 151),
+        ] {
+            assert!(matches!(switchboard_outcome(&l, Some(&recommitted)), Ok(Settlement::Withdrawn(1))));
+        }
+    }
+
+    #[test]
+    fn switchboard_closed_account_loses_for_the_final_joiner() {
+        let mut l = pending_switchboard();
+        assert!(matches!(switchboard_outcome(&l, None), Ok(Settlement::Withdrawn(1))));
+        l.randomness_side = 0;
+        assert!(matches!(switchboard_outcome(&l, None), Ok(Settlement::Withdrawn(0))));
+    }
+
+    // Full, Pending commit-reveal lobby; `revealed` flags in seat order (team1..., team2...)
+    fn pending_commit_reveal(team_size: u8, revealed: &[bool]) -> Lobby {
+        let mut l = lobby_with(RandomnessProvider::CommitReveal, team_size, team_size, 100);
+        seat(&mut l, 0, team_size as usize);
+        seat(&mut l, 1, team_size as usize);
+        for (commitment, revealed) in l.commitments.iter_mut().zip(revealed) {
+            commitment.revealed = *revealed;
+        }
+        l.status = LobbyStatus::Pending;
+        l.pending_since = 10;
+        l
+    }
+
+    fn amounts(l: &Lobby) -> Vec<u64> {
+        forfeit_payouts(l).into_iter().map(|(_, amount)| amount).collect()
+    }
+
+    #[test]
+    fn all_revealed_draws_from_the_reveals() {
+        let key = Pubkey::new_unique();
+        let l = pending_commit_reveal(1, &[true, true]);
+        // no need to wait for the deadline
+        let outcome = reveal_outcome(&key, &l, l.pending_since).unwrap();
+        assert!(matches!(outcome, Settlement::Draw(r) if r == reveal_randomness(&key, &l)));
+    }
+
+    #[test]
+    fn missing_reveal_waits_for_the_deadline() {
+        let key = Pubkey::new_unique();
+        let l = pending_commit_reveal(1, &[true, false]);
+        assert!(reveal_outcome(&key, &l, l.reveal_deadline()).is_err());
+        assert!(matches!(reveal_outcome(&key, &l, l.reveal_deadline() + 1), Ok(Settlement::Forfeit)));
+    }
+
+    #[test]
+    fn one_side_missing_forfeits_to_the_revealer() {
+        let l = pending_commit_reveal(1, &[true, false]);
+        assert_eq!(amounts(&l), vec![200, 0]);
+        let l = pending_commit_reveal(1, &[false, true]);
+        assert_eq!(amounts(&l), vec![0, 200]);
+    }
+
+    #[test]
+    fn forfeit_is_per_player_not_per_side() {
+        // One non-revealer on team1: only their stake is forfeited, their teammate keeps theirs
+        let l = pending_commit_reveal(2, &[true, false, true, true]);
+        assert_eq!(amounts(&l), vec![133, 0, 133, 133]);
+    }
+
+    #[test]
+    fn both_sides_missing_forfeit_to_the_remaining_revealers() {
+        let l = pending_commit_reveal(2, &[true, false, false, true]);
+        assert_eq!(amounts(&l), vec![200, 0, 0, 200]);
+    }
+
+    #[test]
+    fn nobody_revealed_refunds_everyone() {
+        let l = pending_commit_reveal(1, &[false, false]);
+        assert_eq!(amounts(&l), vec![100, 100]);
+        let key = Pubkey::new_unique();
+        assert!(matches!(reveal_outcome(&key, &l, l.reveal_deadline() + 1), Ok(Settlement::Forfeit)));
+    }
+
+    #[test]
+    fn forfeit_is_won_by_the_revealer() {
+        assert_eq!(forfeit_winner(&pending_commit_reveal(1, &[true, false])), Some(0));
+        assert_eq!(forfeit_winner(&pending_commit_reveal(1, &[false, true])), Some(1));
+        assert_eq!(forfeit_winner(&pending_commit_reveal(1, &[false, false])), None);
+    }
+
+    #[test]
+    fn forfeits_never_pay_out_more_than_the_pot() {
+        for revealed in [[true, false, true, true], [true, false, false, false], [false, true, true, false]] {
+            let l = pending_commit_reveal(2, &revealed);
+            assert!(amounts(&l).iter().sum::<u64>() <= l.pot());
+        }
+    }
 }
@@ -0,0 +1,224 @@
+// --------------------------------------------------------------------------
+// Deterministic RNG stream over a 64-byte VRF output
+// --------------------------------------------------------------------------
+// Pure Rust (no Solana / Anchor types) so it can be reused by any game logic
+// and exercised off-chain: the same randomness always yields the same values.
+//
+// Stream: block_i = sha256(DOMAIN || randomness || i as u64 LE), consumed 8 bytes
+// at a time as little-endian u64. Every helper draws from this one stream, so the
+// order of calls is part of the result (resolve_match draws the winner first).
+//
+// Ranges use rejection sampling, so there is no modulo bias for any bound.
+
+use sha2::{Digest, Sha256};
+
+const DOMAIN: &[u8] = b"solanapvp:rng:v1";
+
+pub struct RngStream {
+    randomness: [u8; 64],
+    counter: u64,
+    block: [u8; 32],
+    offset: usize, // next unread byte in `block`
+}
+
+impl RngStream {
+    pub fn new(randomness: &[u8; 64]) -> Self {
+        Self {
+            randomness: *randomness,
+            counter: 0,
+            block: [0u8; 32],
+            offset: 32, // forces the first refill
+        }
+    }
+
+    fn refill(&mut self) {
+        let mut hasher = Sha256::new();
+        hasher.update(DOMAIN);
+        hasher.update(self.randomness);
+        hasher.update(self.counter.to_le_bytes());
+        self.block.copy_from_slice(&hasher.finalize());
+        self.counter += 1;
+        self.offset = 0;
+    }
+
+    // Next raw 64-bit value of the stream
+    pub fn next_u64(&mut self) -> u64 {
+        if self.offset + 8 > self.block.len() {
+            self.refill();
+        }
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&self.block[self.offset..self.offset + 8]);
+        self.offset += 8;
+        u64::from_le_bytes(bytes)
+    }
+
+    // Uniform value in [0, n). None if n == 0.
+    pub fn below(&mut self, n: u64) -> Option<u64> {
+        if n == 0 {
+            return None;
+        }
+        // 2^64 mod n values at the top of the u64 range would favour small results: redraw those
+        let reject_from = u64::MAX - (u64::MAX % n + 1) % n;
+        loop {
+            let x = self.next_u64();
+            if x <= reject_from {
+                return Some(x % n);
+            }
+        }
+    }
+
+    // Uniform value in [low, high). None if the range is empty.
+    pub fn range(&mut self, low: u64, high: u64) -> Option<u64> {
+        if high <= low {
+            return None;
+        }
+        self.below(high - low).map(|x| low + x)
+    }
+
+    // Uniform permutation (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            // below(i + 1) is Some for any i
+            let j = self.below(i as u64 + 1).unwrap_or(0) as usize;
+            items.swap(i, j);
+        }
+    }
+
+    // Index chosen with probability weights[i] / sum(weights).
+    // None if there are no weights, they are all zero, or their sum overflows u64.
+    pub fn weighted_index(&mut self, weights: &[u64]) -> Option<usize> {
+        let total = weights.iter().try_fold(0u64, |acc, w| acc.checked_add(*w))?;
+        let mut target = self.below(total)?;
+        for (i, w) in weights.iter().enumerate() {
+            if target < *w {
+                return Some(i);
+            }
+            target -= w;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(seed: u8) -> RngStream {
+        RngStream::new(&[seed; 64])
+    }
+
+    #[test]
+    fn same_randomness_same_stream() {
+        let (mut a, mut b) = (stream(7), stream(7));
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(stream(7).next_u64(), stream(8).next_u64());
+    }
+
+    #[test]
+    fn stream_refills_past_the_first_block() {
+        // 4 values per sha256 block: the 5th comes from the next block and must differ from the 1st
+        let mut rng = stream(1);
+        let values: Vec<u64> = (0..8).map(|_| rng.next_u64()).collect();
+        assert_ne!(values[..4], values[4..]);
+    }
+
+    #[test]
+    fn below_stays_in_bounds() {
+        let mut rng = stream(2);
+        assert_eq!(rng.below(0), None);
+        assert_eq!(rng.below(1), Some(0));
+        for n in [2, 3, 7, 10, 1_000, u64::MAX] {
+            for _ in 0..200 {
+                assert!(rng.below(n).unwrap() < n);
+            }
+        }
+    }
+
+    #[test]
+    fn below_redraws_values_in_the_biased_tail() {
+        // n = 2^63 + 1: only draws <= 2^63 are accepted, so about half are redrawn
+        let n = (1u64 << 63) + 1;
+        let mut rejected = 0;
+        for seed in 0..32 {
+            let mut raw = stream(seed);
+            let expected = loop {
+                let x = raw.next_u64();
+                if x <= 1u64 << 63 {
+                    break x % n;
+                }
+                rejected += 1;
+            };
+            let mut rng = stream(seed);
+            assert_eq!(rng.below(n), Some(expected));
+            // both streams consumed the same number of draws
+            assert_eq!(rng.next_u64(), raw.next_u64());
+        }
+        assert!(rejected > 0);
+    }
+
+    #[test]
+    fn below_never_redraws_powers_of_two() {
+        let mut raw = stream(3);
+        let mut rng = stream(3);
+        for _ in 0..100 {
+            assert_eq!(rng.below(1 << 63), Some(raw.next_u64() % (1 << 63)));
+        }
+    }
+
+    #[test]
+    fn empty_range_is_none() {
+        let mut rng = stream(4);
+        assert_eq!(rng.range(5, 5), None);
+        assert_eq!(rng.range(6, 5), None);
+        assert_eq!(rng.range(5, 6), Some(5));
+        for _ in 0..200 {
+            let x = rng.range(10, 20).unwrap();
+            assert!((10..20).contains(&x));
+        }
+    }
+
+    #[test]
+    fn shuffle_is_a_permutation() {
+        let mut rng = stream(5);
+        for len in [0usize, 1, 2, 10, 100] {
+            let mut items: Vec<usize> = (0..len).collect();
+            rng.shuffle(&mut items);
+            let mut sorted = items.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, (0..len).collect::<Vec<_>>());
+        }
+
+        // deterministic for the same randomness
+        let (mut a, mut b): (Vec<u32>, Vec<u32>) = ((0..20).collect(), (0..20).collect());
+        stream(6).shuffle(&mut a);
+        stream(6).shuffle(&mut b);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn weighted_index_skips_zero_weights() {
+        let mut rng = stream(9);
+        for _ in 0..200 {
+            let i = rng.weighted_index(&[0, 3, 0, 5, 0]).unwrap();
+            assert!(i == 1 || i == 3);
+        }
+        assert_eq!(rng.weighted_index(&[0, 0, 1]), Some(2));
+    }
+
+    #[test]
+    fn weighted_index_none_without_weight() {
+        let mut rng = stream(10);
+        assert_eq!(rng.weighted_index(&[]), None);
+        assert_eq!(rng.weighted_index(&[0, 0, 0]), None);
+    }
+
+    #[test]
+    fn weighted_index_none_on_overflowing_weights() {
+        let mut rng = stream(11);
+        assert_eq!(rng.weighted_index(&[u64::MAX, 1]), None);
+        // the largest sum that fits is still fine
+        assert!(rng.weighted_index(&[u64::MAX - 1, 1]).is_some());
+    }
+}
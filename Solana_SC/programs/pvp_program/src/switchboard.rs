@@ -0,0 +1,165 @@
+// --------------------------------------------------------------------------
+// Switchboard On-Demand randomness account (read-only view)
+// --------------------------------------------------------------------------
+// Switchboard On-Demand is pull based: the client creates a randomness account, commits it
+// (Switchboard's randomness_commit ix) in the slot before the final join, and later bundles the
+// oracle's reveal ix with resolve_match. Once revealed, the value stays readable until the account
+// is committed again, so anyone can settle with it afterwards. The account's authority (the final
+// joiner) can still re-commit or close it; the program settles that against their side.
+// Nothing is CPI'd from this program, so instead of pulling in the switchboard-on-demand crate
+// (and its Solana version pins) we read the few fields we need at their fixed offsets in the
+// zero-copy account.
+//
+// Layout after the 8-byte discriminator (#[repr(C)], all fields 8-byte aligned):
+//   authority: Pubkey | queue: Pubkey | seed_slothash: [u8; 32] | seed_slot: u64 |
+//   oracle: Pubkey | reveal_slot: u64 | value: [u8; 32] | reserved (224 bytes)
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::pubkey;
+
+// Switchboard On-Demand program: devnet by default (like declare_id!),
+// mainnet with `--features switchboard-mainnet`.
+#[cfg(not(feature = "switchboard-mainnet"))]
+pub const PROGRAM_ID: Pubkey = pubkey!("Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2");
+#[cfg(feature = "switchboard-mainnet")]
+pub const PROGRAM_ID: Pubkey = pubkey!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+
+// sha256("account:RandomnessAccountData")[..8]
+pub const RANDOMNESS_DISCRIMINATOR: [u8; 8] = [10, 66, 229, 135, 220, 239, 217, 114];
+
+pub const RANDOMNESS_ACCOUNT_SIZE: usize = 8 + 32 + 32 + 32 + 8 + 32 + 8 + 32 + 96 + 128;
+
+const AUTHORITY_OFFSET: usize = 8;
+const SEED_SLOTHASH_OFFSET: usize = 8 + 32 + 32;
+const SEED_SLOT_OFFSET: usize = SEED_SLOTHASH_OFFSET + 32;
+const REVEAL_SLOT_OFFSET: usize = SEED_SLOT_OFFSET + 8 + 32;
+const VALUE_OFFSET: usize = REVEAL_SLOT_OFFSET + 8;
+
+pub struct SwitchboardRandomness {
+    pub authority: Pubkey,       // can commit (re-roll) and close the account
+    pub seed_slothash: [u8; 32], // slot hash the commit was made against (unique per commit)
+    pub seed_slot: u64,          // slot of the commit
+    pub reveal_slot: u64,        // slot of the last reveal
+    pub value: [u8; 32],         // value of the last reveal (for the current commit once reveal_slot > seed_slot)
+}
+
+impl SwitchboardRandomness {
+    // None unless `data` is a RandomnessAccountData account
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < RANDOMNESS_ACCOUNT_SIZE || data[..8] != RANDOMNESS_DISCRIMINATOR {
+            return None;
+        }
+        let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        Some(Self {
+            authority: Pubkey::new_from_array(data[AUTHORITY_OFFSET..AUTHORITY_OFFSET + 32].try_into().unwrap()),
+            seed_slothash: data[SEED_SLOTHASH_OFFSET..SEED_SLOTHASH_OFFSET + 32].try_into().unwrap(),
+            seed_slot: u64_at(SEED_SLOT_OFFSET),
+            reveal_slot: u64_at(REVEAL_SLOT_OFFSET),
+            value: data[VALUE_OFFSET..VALUE_OFFSET + 32].try_into().unwrap(),
+        })
+    }
+
+    // Committed in the slot right before `slot`: nobody (oracle included) can know the value yet
+    pub fn committed_just_before(&self, slot: u64) -> bool {
+        self.seed_slot.checked_add(1) == Some(slot)
+    }
+
+    // Value revealed for the commit made against `seed_slothash`. None until the oracle revealed it,
+    // or once the account has been committed again.
+    pub fn revealed_value(&self, seed_slothash: &[u8; 32]) -> Option<[u8; 32]> {
+        (self.seed_slothash == *seed_slothash && self.reveal_slot > self.seed_slot).then_some(self.value)
+    }
+
+    // Proof that the commit made against `seed_slothash` was never revealed: it is still the
+    // account's current commit and no reveal came after it
+    pub fn unrevealed(&self, seed_slothash: &[u8; 32]) -> bool {
+        self.seed_slothash == *seed_slothash && self.reveal_slot <= self.seed_slot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUTHORITY: Pubkey = pubkey!("7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU");
+    const QUEUE: Pubkey = pubkey!("EYiAmGSdsQTuCw413V5BzaruWuCCSDgTPtBGvLkXHbe7");
+    const ORACLE: Pubkey = pubkey!("GcNZRMqGSEyEULZnLDD3ParcHTgFBrNfUdUCDtThP55e");
+    const SEED_SLOTHASH: [u8; 32] = [0x5a; 32];
+    const VALUE: [u8; 32] = [0xc3; 32];
+
+    // RandomnessAccountData as the Switchboard program lays it out: after randomness_commit at slot
+    // `seed_slot`, and after a reveal at `reveal_slot` (an earlier reveal_slot = not revealed yet)
+    fn account(seed_slothash: [u8; 32], seed_slot: u64, reveal_slot: u64) -> Vec<u8> {
+        let mut data = Vec::with_capacity(RANDOMNESS_ACCOUNT_SIZE);
+        data.extend_from_slice(&RANDOMNESS_DISCRIMINATOR);
+        data.extend_from_slice(AUTHORITY.as_ref());
+        data.extend_from_slice(QUEUE.as_ref());
+        data.extend_from_slice(&seed_slothash);
+        data.extend_from_slice(&seed_slot.to_le_bytes());
+        data.extend_from_slice(ORACLE.as_ref());
+        data.extend_from_slice(&reveal_slot.to_le_bytes());
+        data.extend_from_slice(&VALUE);
+        data.resize(RANDOMNESS_ACCOUNT_SIZE, 0); // reserved tail
+        data
+    }
+
+    #[test]
+    fn account_size_matches_the_on_chain_allocation() {
+        assert_eq!(RANDOMNESS_ACCOUNT_SIZE, 408);
+        assert_eq!(account(SEED_SLOTHASH, 1_000, 0).len(), 408);
+    }
+
+    #[test]
+    fn parses_every_field_at_its_offset() {
+        let parsed = SwitchboardRandomness::parse(&account(SEED_SLOTHASH, 1_000, 1_002)).unwrap();
+        assert_eq!(parsed.authority, AUTHORITY);
+        assert_eq!(parsed.seed_slothash, SEED_SLOTHASH);
+        assert_eq!(parsed.seed_slot, 1_000);
+        assert_eq!(parsed.reveal_slot, 1_002);
+        assert_eq!(parsed.value, VALUE);
+    }
+
+    #[test]
+    fn rejects_other_accounts() {
+        let mut data = account(SEED_SLOTHASH, 1_000, 0);
+        assert!(SwitchboardRandomness::parse(&data[..RANDOMNESS_ACCOUNT_SIZE - 1]).is_none());
+        data[0] ^= 1;
+        assert!(SwitchboardRandomness::parse(&data).is_none());
+    }
+
+    #[test]
+    fn commit_must_be_from_the_previous_slot() {
+        let parsed = SwitchboardRandomness::parse(&account(SEED_SLOTHASH, 1_000, 0)).unwrap();
+        assert!(parsed.committed_just_before(1_001));
+        assert!(!parsed.committed_just_before(1_000));
+        assert!(!parsed.committed_just_before(1_002));
+
+        let parsed = SwitchboardRandomness::parse(&account(SEED_SLOTHASH, u64::MAX, 0)).unwrap();
+        assert!(!parsed.committed_just_before(0));
+    }
+
+    #[test]
+    fn committed_but_not_revealed() {
+        // reveal_slot still points at the previous round's reveal
+        let parsed = SwitchboardRandomness::parse(&account(SEED_SLOTHASH, 1_000, 900)).unwrap();
+        assert!(parsed.unrevealed(&SEED_SLOTHASH));
+        assert_eq!(parsed.revealed_value(&SEED_SLOTHASH), None);
+    }
+
+    #[test]
+    fn revealed_value_stays_readable_after_the_reveal_slot() {
+        let parsed = SwitchboardRandomness::parse(&account(SEED_SLOTHASH, 1_000, 1_002)).unwrap();
+        assert!(!parsed.unrevealed(&SEED_SLOTHASH));
+        assert_eq!(parsed.revealed_value(&SEED_SLOTHASH), Some(VALUE));
+    }
+
+    #[test]
+    fn recommitted_account_is_neither_revealed_nor_unrevealed() {
+        // The lobby's commit (SEED_SLOTHASH) was replaced by a newer one, revealed or not
+        for reveal_slot in [1_500, 2_002] {
+            let parsed = SwitchboardRandomness::parse(&account([0x11; 32], 2_000, reveal_slot)).unwrap();
+            assert!(!parsed.unrevealed(&SEED_SLOTHASH));
+            assert_eq!(parsed.revealed_value(&SEED_SLOTHASH), None);
+        }
+    }
+}
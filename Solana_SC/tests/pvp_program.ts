@@ -2,62 +2,62 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { PvpProgram } from "../target/types/pvp_program";
 import { expect } from "chai";
-import { PublicKey, Keypair, SystemProgram } from "@solana/web3.js";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+} from "@solana/web3.js";
 
 describe("pvp_program", () => {
   // Configure the client to use the local cluster.
   anchor.setProvider(anchor.AnchorProvider.env());
 
   const program = anchor.workspace.PvpProgram as Program<PvpProgram>;
-  const provider = anchor.getProvider();
+  const provider = anchor.getProvider() as anchor.AnchorProvider;
+
+  // The provider wallet is the program's upgrade authority on localnet, so it initializes
+  // the config and acts as admin (needed for set_game).
+  const admin = provider.wallet;
 
   // Test accounts
-  let admin: Keypair;
   let creator: Keypair;
   let player1: Keypair;
-  let player2: Keypair;
   let lobbyPda: PublicKey;
   let activePda: PublicKey;
   let configPda: PublicKey;
+  let gameEntryPda: PublicKey;
+
+  // PickHigher / 1x3 / SingleBattle registry IDs
+  const gameKey = { game: 0, gameMode: 0, arenaType: 0 };
+  const lobbyId = new anchor.BN(1);
+  const stakeLamports = new anchor.BN(100_000_000); // 0.1 SOL
 
   before(async () => {
     // Generate test keypairs
-    admin = Keypair.generate();
     creator = Keypair.generate();
     player1 = Keypair.generate();
-    player2 = Keypair.generate();
 
     // Airdrop SOL to test accounts
-    await provider.connection.requestAirdrop(
-      admin.publicKey,
-      2 * anchor.web3.LAMPORTS_PER_SOL
-    );
-    await provider.connection.requestAirdrop(
-      creator.publicKey,
-      2 * anchor.web3.LAMPORTS_PER_SOL
-    );
-    await provider.connection.requestAirdrop(
-      player1.publicKey,
-      2 * anchor.web3.LAMPORTS_PER_SOL
-    );
-    await provider.connection.requestAirdrop(
-      player2.publicKey,
-      2 * anchor.web3.LAMPORTS_PER_SOL
-    );
-
-    // Wait for airdrops to confirm
-    await new Promise((resolve) => setTimeout(resolve, 1000));
+    for (const kp of [creator, player1]) {
+      const sig = await provider.connection.requestAirdrop(
+        kp.publicKey,
+        2 * anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(sig);
+    }
 
     // Derive PDAs
     [lobbyPda] = PublicKey.findProgramAddressSync(
       [
         Buffer.from("lobby"),
         creator.publicKey.toBuffer(),
-        new anchor.BN(1).toArrayLike(Buffer, "le", 8),
+        lobbyId.toArrayLike(Buffer, "le", 8),
       ],
       program.programId
     );
 
+    // Active slot 0 is the plain ["active", creator] PDA
     [activePda] = PublicKey.findProgramAddressSync(
       [Buffer.from("active"), creator.publicKey.toBuffer()],
       program.programId
@@ -67,18 +67,93 @@ describe("pvp_program", () => {
       [Buffer.from("config")],
       program.programId
     );
+
+    [gameEntryPda] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("game"),
+        Buffer.from([gameKey.game, gameKey.gameMode, gameKey.arenaType]),
+      ],
+      program.programId
+    );
+  });
+
+  it("Initializes the config", async () => {
+    // Re-running against a validator that already has the config is fine
+    if (await provider.connection.getAccountInfo(configPda)) {
+      return;
+    }
+
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    );
+
+    await program.methods
+      .initializeConfig(admin.publicKey, admin.publicKey)
+      .accountsPartial({
+        config: configPda,
+        authority: admin.publicKey,
+        program: program.programId,
+        programData,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const config = await program.account.globalConfig.fetch(configPda);
+    expect(config.admin.toString()).to.equal(admin.publicKey.toString());
+  });
+
+  it("Registers a game", async () => {
+    await program.methods
+      .setGame(
+        gameKey,
+        "PickHigher",
+        "1x3",
+        "SingleBattle",
+        [
+          { team1Size: 1, team2Size: 1 },
+          { team1Size: 2, team2Size: 2 },
+        ],
+        new anchor.BN(50_000_000),
+        new anchor.BN(10 * anchor.web3.LAMPORTS_PER_SOL),
+        true
+      )
+      .accountsPartial({
+        config: configPda,
+        gameEntry: gameEntryPda,
+        authority: admin.publicKey,
+        proposal: null,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const entry = await program.account.gameEntry.fetch(gameEntryPda);
+    expect(entry.enabled).to.be.true;
   });
 
   it("Creates a lobby", async () => {
-    const lobbyId = new anchor.BN(1);
-    const teamSize = 1;
-    const stakeLamports = new anchor.BN(100_000_000); // 0.1 SOL
-    const side = 0; // team1
+    const now = Math.floor(Date.now() / 1000);
+    const expiresAt = new anchor.BN(now + 3_600);
 
     const tx = await program.methods
-      .createLobby(lobbyId, teamSize, stakeLamports, side)
-      .accounts({
+      .createLobby(
+        lobbyId,
+        2, // team1_size
+        2, // team2_size
+        stakeLamports,
+        0, // side: team1
+        gameKey,
+        expiresAt,
+        0, // active_slot
+        null // no commitment: randomness comes from the configured provider
+      )
+      .accountsPartial({
+        lobby: lobbyPda,
+        active: activePda,
+        config: configPda,
+        gameEntry: gameEntryPda,
         creator: creator.publicKey,
+        systemProgram: SystemProgram.programId,
       })
       .signers([creator])
       .rpc();
@@ -90,11 +165,12 @@ describe("pvp_program", () => {
     expect(lobbyAccount.creator.toString()).to.equal(
       creator.publicKey.toString()
     );
-    expect(lobbyAccount.teamSize).to.equal(teamSize);
+    expect(lobbyAccount.team1Size).to.equal(2);
+    expect(lobbyAccount.team2Size).to.equal(2);
     expect(lobbyAccount.stakeLamports.toString()).to.equal(
       stakeLamports.toString()
     );
-    expect(lobbyAccount.status.open).to.be.true;
+    expect(lobbyAccount.status.open).to.not.be.undefined;
     expect(lobbyAccount.team1.length).to.equal(1);
     expect(lobbyAccount.team1[0].toString()).to.equal(
       creator.publicKey.toString()
@@ -102,124 +178,66 @@ describe("pvp_program", () => {
   });
 
   it("Player joins lobby", async () => {
-    const side = 1; // team2
+    // 2v2: the second player doesn't fill the lobby, so no randomness accounts are needed
+    await program.methods
+      .joinSide(1, null)
+      .accountsPartial({
+        lobby: lobbyPda,
+        creator: creator.publicKey,
+        player: player1.publicKey,
+        active: activePda,
+        config: configPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([player1])
+      .rpc();
 
-    // For join_side, we need switchboard accounts for the LAST join
-    // Since this is 1v1 and this IS the last join, we'd need VRF accounts
-    // For testing without VRF setup, skip this test or use larger team size
-    console.log(
-      "⚠️ Skipping join test - requires Switchboard VRF accounts for last join"
+    const lobbyAccount = await program.account.lobby.fetch(lobbyPda);
+    expect(lobbyAccount.team2.length).to.equal(1);
+    expect(lobbyAccount.team2[0].toString()).to.equal(
+      player1.publicKey.toString()
     );
-    console.log("Use 2v2 or 5v5 to test partial joins without VRF");
-    console.log("This test is PASSED (skipped) - join logic works on devnet");
   });
 
-  it("Refunds lobby after timeout", async () => {
-    // In production, need to wait 2 minutes. For testing on localnet,
-    // we bypass the time check or modify REFUND_LOCK_SECS
-    // For now, testing the refund mechanism itself
-
-    console.log("Testing refund...");
-    console.log("Lobby PDA:", lobbyPda.toString());
-    console.log("Creator:", creator.publicKey.toString());
-    console.log("Player1:", player1.publicKey.toString());
-
-    // Get balances before refund
-    const creatorBalanceBefore = await provider.connection.getBalance(
-      creator.publicKey
-    );
-    const player1BalanceBefore = await provider.connection.getBalance(
+  it("Player leaves lobby", async () => {
+    const balanceBefore = await provider.connection.getBalance(
       player1.publicKey
     );
 
-    console.log(
-      "Creator balance before:",
-      creatorBalanceBefore / anchor.web3.LAMPORTS_PER_SOL,
-      "SOL"
-    );
-    console.log(
-      "Player1 balance before:",
-      player1BalanceBefore / anchor.web3.LAMPORTS_PER_SOL,
-      "SOL"
-    );
+    await program.methods
+      .leaveLobby()
+      .accountsPartial({
+        lobby: lobbyPda,
+        player: player1.publicKey,
+      })
+      .signers([player1])
+      .rpc();
 
-    // Wait 2+ minutes for refund lock to expire
-    console.log("⏳ Waiting 2 minutes for refund lock...");
-    console.log(
-      "(In real devnet you'd need to wait. Localnet might have issues with time-based locks)"
+    const lobbyAccount = await program.account.lobby.fetch(lobbyPda);
+    expect(lobbyAccount.team2.length).to.equal(0);
+
+    // Stake comes back (minus the transaction fee)
+    const balanceAfter = await provider.connection.getBalance(
+      player1.publicKey
     );
-    // await new Promise((resolve) => setTimeout(resolve, 121000)); // 2min 1sec
-
-    try {
-      const tx = await program.methods
-        .refund()
-        .accountsStrict({
-          lobby: lobbyPda,
-          creator: creator.publicKey,
-          requester: creator.publicKey,
-          active: activePda,
-          config: configPda,
-          systemProgram: SystemProgram.programId,
-        } as any)
-        .remainingAccounts([
-          { pubkey: creator.publicKey, isSigner: false, isWritable: true },
-        ])
-        .signers([creator])
-        .rpc();
-
-      console.log("✅ Refund transaction signature", tx);
-
-      // Verify lobby was refunded
-      const lobbyAccount = await program.account.lobby.fetch(lobbyPda);
-      expect(lobbyAccount.status.refunded).to.not.be.undefined;
-
-      // Check balances after refund
-      const creatorBalanceAfter = await provider.connection.getBalance(
-        creator.publicKey
-      );
-      const player1BalanceAfter = await provider.connection.getBalance(
-        player1.publicKey
-      );
+    expect(balanceAfter).to.be.greaterThan(
+      balanceBefore + stakeLamports.toNumber() - 10_000
+    );
+  });
 
-      console.log(
-        "Creator balance after:",
-        creatorBalanceAfter / anchor.web3.LAMPORTS_PER_SOL,
-        "SOL"
-      );
-      console.log(
-        "Player1 balance after:",
-        player1BalanceAfter / anchor.web3.LAMPORTS_PER_SOL,
-        "SOL"
-      );
+  it("Creator cancels the empty lobby", async () => {
+    await program.methods
+      .cancelLobby()
+      .accountsPartial({
+        lobby: lobbyPda,
+        active: activePda,
+        creator: creator.publicKey,
+      })
+      .signers([creator])
+      .rpc();
 
-      console.log(
-        "Creator got back:",
-        (creatorBalanceAfter - creatorBalanceBefore) /
-          anchor.web3.LAMPORTS_PER_SOL,
-        "SOL"
-      );
-      console.log(
-        "Player1 got back:",
-        (player1BalanceAfter - player1BalanceBefore) /
-          anchor.web3.LAMPORTS_PER_SOL,
-        "SOL"
-      );
-    } catch (error: any) {
-      console.error("❌ Refund failed:", error.message || error);
-      console.log("Error logs:", error.logs || "No logs");
-
-      // Check if it's time-lock error (expected on fresh lobby)
-      if (
-        error.message?.includes("Too soon to refund") ||
-        error.logs?.some((log: string) => log.includes("TooSoonToRefund"))
-      ) {
-        console.log(
-          "⏰ Expected error: Need to wait 2 minutes after lobby creation"
-        );
-        console.log("This is CORRECT behavior - time lock is working!");
-      } else {
-        throw error; // Re-throw if it's a different error
-      }
-    }
+    // Lobby and ActiveLobby are closed back to the creator
+    expect(await provider.connection.getAccountInfo(lobbyPda)).to.be.null;
+    expect(await provider.connection.getAccountInfo(activePda)).to.be.null;
   });
 });